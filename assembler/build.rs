@@ -1,8 +1,9 @@
-use std::{env, fs::File, io::{self, Write}, path::PathBuf};
+use std::{env, fs::{self, File}, io::{self, Write}, path::PathBuf};
 
 use glob::glob;
 
 /// Generate tests for files in ./tests/data.
+#[allow(clippy::explicit_counter_loop)]
 fn main() -> io::Result<()> {
     // Addind/Removing files in ./tests/data should trigger a rebuild.
     println!("cargo:rerun-if-changed=tests/data");
@@ -26,6 +27,31 @@ fn main() -> io::Result<()> {
             asm_path = asm_file.display(),
             bin_path = bin_file.display(),
         )?;
+        writeln!(
+            out_file,
+            include_str!("tests/templates/disassemble_test.trs"),
+            number = i,
+            bin_path = bin_file.display(),
+        )?;
+        writeln!(
+            out_file,
+            include_str!("tests/templates/decode_test.trs"),
+            number = i,
+            asm_path = asm_file.display(),
+            bin_path = bin_file.display(),
+        )?;
+
+        // Files with `; expect: ...` comments are also executed.
+        let source = fs::read_to_string(&asm_file)?;
+        let has_expectations = source.lines().any(|line| line.split_once(';').is_some_and(|(_, comment)| comment.trim_start().starts_with("expect:")));
+        if has_expectations {
+            writeln!(
+                out_file,
+                include_str!("tests/templates/run_test.trs"),
+                number = i,
+                asm_path = asm_file.display(),
+            )?;
+        }
 
         i += 1;
     }
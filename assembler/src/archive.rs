@@ -0,0 +1,61 @@
+use std::{collections::HashSet, fs, io, path::Path};
+
+use serde::{Deserialize, Serialize};
+
+use crate::object::Object;
+
+/// File extension of archive files.
+pub const ARCHIVE_EXTENSION: &str = "a";
+
+/// A library of objects. Members are only linked into a program if they
+/// define a symbol that the program references.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Archive {
+    pub members: Vec<Object>,
+}
+
+impl Archive {
+    pub fn read<P: AsRef<Path>>(path: P) -> io::Result<Archive> {
+        let content = fs::read_to_string(path)?;
+        serde_json::from_str(&content).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+
+    pub fn write<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let content = serde_json::to_string(self).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        fs::write(path, content)
+    }
+}
+
+/// Selects the archive members which define symbols that are undefined in
+/// `objects`. Symbols needed by selected members are resolved as well.
+/// Archives are searched in order, the first member defining a symbol is used.
+pub fn select_members(objects: &[Object], archives: &[Archive]) -> Vec<Object> {
+    let mut selected: Vec<&Object> = Vec::new();
+    let mut defined = objects
+        .iter()
+        .flat_map(Object::global_symbols)
+        .map(|symbol| symbol.name.as_str())
+        .collect::<HashSet<_>>();
+    let mut undefined = objects.iter().flat_map(Object::undefined_symbols).collect::<Vec<_>>();
+
+    while let Some(symbol) = undefined.pop() {
+        if defined.contains(symbol) {
+            continue;
+        }
+
+        let member = archives
+            .iter()
+            .flat_map(|archive| &archive.members)
+            .find(|member| member.global_symbols().any(|global| global.name == symbol));
+
+        // Symbols that cannot be found are reported by the linker.
+        if let Some(member) = member {
+            defined.extend(member.global_symbols().map(|global| global.name.as_str()));
+            undefined.extend(member.undefined_symbols());
+            selected.push(member);
+        }
+        defined.insert(symbol);
+    }
+
+    selected.into_iter().cloned().collect()
+}
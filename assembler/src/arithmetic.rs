@@ -0,0 +1,99 @@
+/// Operations of Factorio's arithmetic combinator with its integer semantics,
+/// which the CPU inherits:
+///
+/// * All results wrap around on 32 bit overflow, including `i32::MIN / -1`.
+/// * Division truncates towards 0, the remainder has the sign of the dividend.
+/// * Division and modulo by 0 result in 0.
+/// * Powers with a negative exponent result in 0.
+/// * Shift amounts are masked to 5 bits and `>>` retains the sign.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Operation {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Mod,
+    Pow,
+    Shl,
+    Shr,
+    And,
+    Or,
+    Xor,
+}
+
+impl Operation {
+    pub const ALL: [Operation; 11] = [
+        Operation::Add,
+        Operation::Sub,
+        Operation::Mul,
+        Operation::Div,
+        Operation::Mod,
+        Operation::Pow,
+        Operation::Shl,
+        Operation::Shr,
+        Operation::And,
+        Operation::Or,
+        Operation::Xor,
+    ];
+
+    /// Symbol of the operation in-game and in blueprints, e.g. `+` or `AND`.
+    pub fn symbol(self) -> &'static str {
+        match self {
+            Operation::Add => "+",
+            Operation::Sub => "-",
+            Operation::Mul => "*",
+            Operation::Div => "/",
+            Operation::Mod => "%",
+            Operation::Pow => "^",
+            Operation::Shl => "<<",
+            Operation::Shr => ">>",
+            Operation::And => "AND",
+            Operation::Or => "OR",
+            Operation::Xor => "XOR",
+        }
+    }
+
+    pub fn from_symbol(symbol: &str) -> Option<Operation> {
+        Operation::ALL.into_iter().find(|operation| operation.symbol() == symbol)
+    }
+
+    pub fn apply(self, a: i32, b: i32) -> i32 {
+        match self {
+            Operation::Add => a.wrapping_add(b),
+            Operation::Sub => a.wrapping_sub(b),
+            Operation::Mul => a.wrapping_mul(b),
+            Operation::Div => div(a, b),
+            Operation::Mod => modulo(a, b),
+            Operation::Pow => pow(a, b),
+            Operation::Shl => a.wrapping_shl(b as u32),
+            Operation::Shr => a.wrapping_shr(b as u32),
+            Operation::And => a & b,
+            Operation::Or => a | b,
+            Operation::Xor => a ^ b,
+        }
+    }
+}
+
+fn div(a: i32, b: i32) -> i32 {
+    if b == 0 {
+        0
+    } else {
+        a.wrapping_div(b)
+    }
+}
+
+fn modulo(a: i32, b: i32) -> i32 {
+    if b == 0 {
+        0
+    } else {
+        a.wrapping_rem(b)
+    }
+}
+
+fn pow(a: i32, b: i32) -> i32 {
+    if b < 0 {
+        0
+    } else {
+        a.wrapping_pow(b as u32)
+    }
+}
@@ -1,10 +1,14 @@
 use std::collections::{HashMap, HashSet};
 
-use crate::ir::{IRCommand, IRInstruction, IRLine, IRParamType, IR, IRParameter};
+use crate::{
+    debug_info::LineInfo,
+    ir::{IRCommand, IRDirective, IRInstruction, IRLine, IRParamType, IR, IRParameter},
+    object::{MemoryOperand, Object, ObjectSymbol, Relocation, RelocationKind, Section, SectionKind, DEFAULT_SECTION},
+};
 
-type InstructionSignature = (IRCommand, IRParamType, IRParamType);
+pub type InstructionSignature = (IRCommand, IRParamType, IRParamType);
 
-struct AssemblyTranslation {
+pub(crate) struct AssemblyTranslation {
     instructions: HashMap<InstructionSignature, u8>,
 
     /// Indicates which instructions have byte sized immediates
@@ -16,15 +20,18 @@ struct AssembleInstruction<'a> {
     instruction: &'a IRInstruction,
     assembled: Vec<u8>,
     next_register: usize,
+    /// Words (relative to the instruction) which hold the absolute address of a label.
+    label_words: Vec<(usize, &'a str)>,
 }
 
 pub const HALT_INSTRUCTION: u8 = 0xee;
+pub const MOV_REG_IMM_INSTRUCTION: u8 = 0x01;
 
 impl AssemblyTranslation {
-    fn new() -> AssemblyTranslation {
+    pub(crate) fn new() -> AssemblyTranslation {
         let instructions = HashMap::from([
             // MOV
-            ((IRCommand::Mov, IRParamType::Register, IRParamType::Immediate), 0x01),
+            ((IRCommand::Mov, IRParamType::Register, IRParamType::Immediate), MOV_REG_IMM_INSTRUCTION),
             ((IRCommand::Mov, IRParamType::Register, IRParamType::Register), 0x02),
             ((IRCommand::Mov, IRParamType::Register, IRParamType::MemoryAtImmediate), 0x03),
             ((IRCommand::Mov, IRParamType::Register, IRParamType::MemoryAtRegister), 0x04),
@@ -101,7 +108,16 @@ impl AssemblyTranslation {
         }
     }
 
-    fn assemble_instruction(&self, instruction: &IRInstruction) -> Vec<u8> {
+    /// Maps instruction type bytes back to their signature and whether
+    /// they use byte sized immediates.
+    pub(crate) fn decoding_table(&self) -> HashMap<u8, (InstructionSignature, bool)> {
+        self.instructions
+            .iter()
+            .map(|(signature, &encoding)| (encoding, (signature.clone(), self.byte_immediates.contains(signature))))
+            .collect()
+    }
+
+    fn assemble_instruction<'a>(&self, instruction: &'a IRInstruction) -> AssembleInstruction<'a> {
         let mut assemble = AssembleInstruction::new(instruction);
 
         // Encode instruction type byte.
@@ -119,7 +135,7 @@ impl AssemblyTranslation {
         let byte_immediates = self.byte_immediates.contains(&instruction_signature);
         assemble.assemble(encoding, byte_immediates);
 
-        assemble.assembled
+        assemble
     }
 }
 
@@ -129,6 +145,7 @@ impl<'a> AssembleInstruction<'a> {
             instruction,
             assembled: vec![0u8; 4],
             next_register: 2,
+            label_words: Vec::new(),
         }
     }
 
@@ -138,7 +155,7 @@ impl<'a> AssembleInstruction<'a> {
         self.assemble_parameter(&self.instruction.param2, byte_immediates);
     }
 
-    fn assemble_parameter(&mut self, param: &Option<IRParameter>, byte_immediates: bool) {
+    fn assemble_parameter(&mut self, param: &'a Option<IRParameter>, byte_immediates: bool) {
         match param {
             Some(IRParameter::Reg(register)) | Some(IRParameter::MemReg(register)) => {
                 self.add_register_value(*register as u8);
@@ -155,6 +172,15 @@ impl<'a> AssembleInstruction<'a> {
                     self.add_register_value(value as u8);
                 }
             }
+            Some(IRParameter::Label(label)) | Some(IRParameter::MemLabel(label)) if !self.instruction.command.is_relative_jump() => {
+                if byte_immediates {
+                    panic!("Instruction {:?} cannot use label '{}' as byte parameter (on line {})", self.instruction.command, label, self.instruction.line_number);
+                }
+
+                // The address is filled in by the linker.
+                self.label_words.push((self.assembled.len() / 4, label));
+                self.assembled.extend_from_slice(&[0u8; 4]);
+            }
             _ => {}
         }
     }
@@ -165,71 +191,180 @@ impl<'a> AssembleInstruction<'a> {
     }
 }
 
-fn param_type(param: &Option<IRParameter>) -> IRParamType {
+fn param_type(command: &IRCommand, param: &Option<IRParameter>) -> IRParamType {
     match param {
+        // Outside of jumps, labels are used as immediate addresses.
+        Some(IRParameter::Label(_)) if !command.is_relative_jump() => IRParamType::Immediate,
         Some(p) => p.param_type(),
         None => IRParamType::None,
     }
 }
 
 fn instruction_signature(instruction: &IRInstruction) -> InstructionSignature {
-    (instruction.command.clone(), param_type(&instruction.param1), param_type(&instruction.param2))
+    let command = &instruction.command;
+    (command.clone(), param_type(command, &instruction.param1), param_type(command, &instruction.param2))
 }
 
-pub fn assemble(ir: IR) -> Vec<u8> {
+/// Assembles the given IR into an object. Jumps to labels within the same
+/// section are resolved immediately, all other label references are left to the linker.
+pub fn assemble(ir: IR) -> Object {
     let translation = AssemblyTranslation::new();
-    let mut assembled = Vec::with_capacity(ir.instructions.len());
     let mut label_locations = HashMap::new();
+    let mut globals = HashSet::new();
+    let mut externs = HashSet::new();
+    let mut object = Object {
+        source: ir.source.clone(),
+        sections: vec![Section::new(DEFAULT_SECTION, SectionKind::Text)],
+        externs: Vec::new(),
+    };
+    let mut current = 0;
+
+    // Label references, which can only be resolved once all labels are known:
+    // (section, word, label, line number)
+    let mut jumps = Vec::new();
+    let mut addresses = Vec::new();
 
     // First scan to figure out size and assemble all but labels.
-    let mut location = 0;
     for instruction in &ir.instructions {
+        let section = &mut object.sections[current];
+        let location = section.size();
         match instruction {
             IRLine::Ins(ins) => {
+                if section.kind == SectionKind::Bss {
+                    panic!("Instructions are not allowed in bss section '{}' (on line {})", section.name, ins.line_number);
+                }
+
                 let translated = translation.assemble_instruction(ins);
-                location += translated.len() / 4;
-                assembled.push(translated);
+                let size = (translated.assembled.len() / 4) as u32;
+                section.lines.push(LineInfo {
+                    file: 0,
+                    line: ins.line_number,
+                    start: location,
+                    end: location + size,
+                    data: false,
+                });
+                for param in [&ins.param1, &ins.param2] {
+                    if let Some(IRParameter::MemImm(address)) = param {
+                        section.memory_operands.push(MemoryOperand { address: *address, line: ins.line_number });
+                    }
+                }
+
+                if let Some(IRParameter::Label(target_label)) = &ins.param1 {
+                    if ins.command.is_relative_jump() {
+                        jumps.push((current, location, target_label.as_str(), ins.line_number));
+                    }
+                }
+                for &(index, label) in &translated.label_words {
+                    addresses.push((current, location + index as u32, label, ins.line_number));
+                }
+
+                section.words.extend(translated.assembled.chunks(4).map(|word| u32::from_be_bytes(word.try_into().unwrap())));
+                // The linker adds HALT to the end of the result if it is not present.
+                section.ends_with_halt = ins.command == IRCommand::Halt;
             }
             IRLine::Label(label) => {
-                if label_locations.insert(label, location).is_some() {
+                if label_locations.insert(label.as_str(), (current, location)).is_some() {
                     panic!("Found duplicate label: '{}'", label);
                 }
+                section.symbols.push(ObjectSymbol {
+                    name: label.clone(),
+                    offset: location,
+                    global: false,
+                });
+                section.ends_with_halt = false;
             }
-        }
-    }
-
-    // Add HALT to the end of the result if it is not present.
-    match &ir.instructions.iter().last() {
-        Some(IRLine::Ins(ins)) if ins.command == IRCommand::Halt => {}
-        _ => assembled.push(vec![0x00, 0x00, 0x00, HALT_INSTRUCTION])
-    }
+            IRLine::Directive(IRDirective::Global(names), _) => globals.extend(names.iter().map(String::as_str)),
+            IRLine::Directive(IRDirective::Extern(names), _) => externs.extend(names.iter().map(String::as_str)),
+            IRLine::Directive(IRDirective::Section(name, kind), _) => {
+                current = match object.sections.iter().position(|section| &section.name == name) {
+                    Some(index) if object.sections[index].kind != *kind => {
+                        panic!("Section '{}' is used as {:?} and {:?} section", name, object.sections[index].kind, kind);
+                    }
+                    Some(index) => index,
+                    None => {
+                        object.sections.push(Section::new(name, *kind));
+                        object.sections.len() - 1
+                    }
+                };
+            }
+            IRLine::Directive(IRDirective::Word(values), line_number) => {
+                if section.kind == SectionKind::Bss {
+                    panic!("Initialized words are not allowed in bss section '{}' (on line {})", section.name, *line_number);
+                }
 
-    // Second scan to add location to jump instructions.
-    let mut location = 0;
-    let mut assembled_index = 0;
-    for instruction in &ir.instructions {
-        match instruction {
-            IRLine::Ins(ins) => {
-                if let Some(IRParameter::Label(target_label)) = &ins.param1 {
-                    let label_location = match label_locations.get(target_label) {
-                        Some(location) => *location,
-                        None => panic!("Did not find target label '{}'", target_label),
-                    };
-
-                    let location_difference = (label_location as i32) - (location as i32);
-                    let encoded_location = location_difference.to_be_bytes();
-                    for i in 0..3 {
-                        assembled[assembled_index][i] = encoded_location[i+1];
+                for (index, value) in values.iter().enumerate() {
+                    match value {
+                        IRParameter::Imm(value) => section.words.push(*value as u32),
+                        IRParameter::Label(label) => {
+                            addresses.push((current, location + index as u32, label.as_str(), *line_number));
+                            section.words.push(0);
+                        }
+                        _ => unreachable!("Words are either immediates or labels"),
                     }
                 }
+                section.lines.push(LineInfo {
+                    file: 0,
+                    line: *line_number,
+                    start: location,
+                    end: section.size(),
+                    data: true,
+                });
+                section.ends_with_halt = false;
+            }
+            IRLine::Directive(IRDirective::Space(count), line_number) => {
+                section.words.resize((location + count) as usize, 0);
+                section.lines.push(LineInfo {
+                    file: 0,
+                    line: *line_number,
+                    start: location,
+                    end: section.size(),
+                    data: true,
+                });
+                section.ends_with_halt = false;
+            }
+        }
+    }
 
-                location += assembled[assembled_index].len() / 4;
-                assembled_index += 1;
+    if let Some(name) = globals.iter().find(|&&name| !label_locations.contains_key(name)) {
+        panic!("Global label '{}' is not defined", name);
+    }
+    for symbol in object.sections.iter_mut().flat_map(|section| &mut section.symbols) {
+        symbol.global = globals.contains(symbol.name.as_str());
+    }
+    object.externs = externs.iter().map(|&name| name.into()).collect();
+    object.externs.sort();
+
+    // Second scan to add location to jump instructions and collect references for the linker.
+    for (current, location, target_label, line_number) in jumps {
+        let section = &mut object.sections[current];
+        let word = &mut section.words[location as usize];
+        match label_locations.get(target_label).filter(|(label_section, _)| *label_section == current) {
+            Some(&(_, label_location)) => {
+                *word = RelocationKind::Relative.patch(*word, location, label_location)
+                    .unwrap_or_else(|| panic!("Target label '{}' is out of range (on line {})", target_label, line_number));
             }
-            _ => {}
+            // Labels in other sections are resolved once the sections are placed.
+            None if label_locations.contains_key(target_label) || externs.contains(target_label) => section.relocations.push(Relocation {
+                offset: location,
+                kind: RelocationKind::Relative,
+                symbol: target_label.into(),
+            }),
+            None => panic!("Did not find target label '{}' (on line {})", target_label, line_number),
         }
     }
 
+    for (current, location, label, line_number) in addresses {
+        if !label_locations.contains_key(label) && !externs.contains(label) {
+            panic!("Did not find target label '{}' (on line {})", label, line_number);
+        }
+        object.sections[current].relocations.push(Relocation {
+            offset: location,
+            kind: RelocationKind::Absolute,
+            symbol: label.into(),
+        });
+    }
 
-    assembled.into_iter().flatten().collect()
+    // Sections which were switched away from before anything was put into them are dropped.
+    object.sections.retain(|section| !section.words.is_empty() || !section.symbols.is_empty());
+    object
 }
@@ -0,0 +1,179 @@
+use std::{collections::BTreeMap, fs, io, path::Path};
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use flate2::{read::ZlibDecoder, write::ZlibEncoder, Compression};
+use serde::{Deserialize, Serialize};
+
+/// Version byte at the start of every blueprint string.
+const VERSION_PREFIX: char = '0';
+
+/// Factorio blueprint, as far as it is needed to describe circuit networks.
+/// Blueprint strings are `0` followed by the base64 encoded, zlib compressed JSON:
+///
+/// ```json
+/// { "blueprint": { "item": "blueprint", "entities": [ ... ] } }
+/// ```
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Blueprint {
+    pub item: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+    #[serde(default)]
+    pub entities: Vec<Entity>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<u64>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+struct BlueprintString {
+    blueprint: Blueprint,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Entity {
+    pub entity_number: u32,
+    pub name: String,
+    pub position: Position,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub direction: Option<u8>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub control_behavior: Option<ControlBehavior>,
+    /// Wires per connection point. `"1"` is the input and `"2"` the output of
+    /// arithmetic and decider combinators, all other entities only have `"1"`.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub connections: BTreeMap<String, Connection>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct Position {
+    pub x: f64,
+    pub y: f64,
+}
+
+#[derive(Serialize, Deserialize, Default, Debug, Clone, PartialEq)]
+pub struct Connection {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub red: Vec<Wire>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub green: Vec<Wire>,
+}
+
+/// Other end of a wire.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Wire {
+    pub entity_id: u32,
+    /// Connection point of the other entity, `1` if not given.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub circuit_id: Option<u8>,
+}
+
+#[derive(Serialize, Deserialize, Default, Debug, Clone, PartialEq)]
+pub struct ControlBehavior {
+    /// Signals of a constant combinator.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub filters: Option<Vec<Filter>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub is_on: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub arithmetic_conditions: Option<ArithmeticConditions>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub decider_conditions: Option<DeciderConditions>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Filter {
+    pub signal: SignalId,
+    pub count: i32,
+    pub index: u32,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct SignalId {
+    /// `virtual`, `item` or `fluid`.
+    #[serde(rename = "type")]
+    pub kind: String,
+    pub name: String,
+}
+
+#[derive(Serialize, Deserialize, Default, Debug, Clone, PartialEq)]
+pub struct ArithmeticConditions {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub first_signal: Option<SignalId>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub first_constant: Option<i32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub second_signal: Option<SignalId>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub second_constant: Option<i32>,
+    /// Symbol of the operation, e.g. `*` or `AND`.
+    pub operation: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output_signal: Option<SignalId>,
+}
+
+#[derive(Serialize, Deserialize, Default, Debug, Clone, PartialEq)]
+pub struct DeciderConditions {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub first_signal: Option<SignalId>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub second_signal: Option<SignalId>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub constant: Option<i32>,
+    /// One of `<`, `>`, `=`, `≥`, `≤` or `≠`.
+    pub comparator: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output_signal: Option<SignalId>,
+    /// Output the input value of the signal instead of 1.
+    #[serde(default = "default_copy_count_from_input")]
+    pub copy_count_from_input: bool,
+}
+
+fn default_copy_count_from_input() -> bool {
+    true
+}
+
+impl SignalId {
+    /// Virtual signal like `signal-A`, `signal-0`, `signal-pink` or `signal-each`.
+    pub fn virtual_signal(name: &str) -> SignalId {
+        SignalId {
+            kind: "virtual".into(),
+            name: name.into(),
+        }
+    }
+}
+
+impl Blueprint {
+    pub fn new(label: &str, entities: Vec<Entity>) -> Blueprint {
+        Blueprint {
+            item: "blueprint".into(),
+            label: Some(label.into()),
+            entities,
+            version: None,
+        }
+    }
+
+    /// Reads a file containing a blueprint string.
+    pub fn read<P: AsRef<Path>>(path: P) -> io::Result<Blueprint> {
+        let content = fs::read_to_string(path)?;
+        Blueprint::decode(&content).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+
+    pub fn write<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        fs::write(path, self.encode() + "\n")
+    }
+
+    pub fn decode(string: &str) -> Result<Blueprint, String> {
+        let encoded = string.trim().strip_prefix(VERSION_PREFIX).ok_or("Blueprint string does not start with version 0")?;
+        let compressed = STANDARD.decode(encoded).map_err(|e| format!("Invalid base64 in blueprint string: {}", e))?;
+        let json = ZlibDecoder::new(compressed.as_slice());
+        let string: BlueprintString = serde_json::from_reader(json).map_err(|e| format!("Invalid blueprint: {}", e))?;
+        Ok(string.blueprint)
+    }
+
+    pub fn encode(&self) -> String {
+        let mut encoder = ZlibEncoder::new(Vec::new(), Compression::best());
+        serde_json::to_writer(&mut encoder, &BlueprintString { blueprint: self.clone() }).expect("Could not compress blueprint");
+        let compressed = encoder.finish().expect("Could not compress blueprint");
+        format!("{}{}", VERSION_PREFIX, STANDARD.encode(compressed))
+    }
+}
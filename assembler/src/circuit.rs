@@ -0,0 +1,473 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+use crate::{
+    arithmetic::Operation,
+    blueprint::{ArithmeticConditions, Blueprint, DeciderConditions, Entity, SignalId},
+};
+
+/// Values of the signals on a network, by signal name like `signal-A`.
+/// Signals with the value 0 are not present.
+pub type Signals = BTreeMap<String, i32>;
+
+pub const EACH: &str = "signal-each";
+pub const ANYTHING: &str = "signal-anything";
+pub const EVERYTHING: &str = "signal-everything";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum WireColor {
+    Red,
+    Green,
+}
+
+/// Connection point of an entity: `1` is the input and `2` the output of
+/// arithmetic and decider combinators. Constant combinators output on `1`.
+pub type Point = u8;
+
+/// Connection points joined by wires of the same color.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Network {
+    pub color: WireColor,
+    /// Entity numbers and connection points, sorted.
+    pub points: Vec<(u32, Point)>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Operand {
+    Signal(String),
+    Constant(i32),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Comparator {
+    Less,
+    Greater,
+    Equal,
+    GreaterOrEqual,
+    LessOrEqual,
+    NotEqual,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Behavior {
+    Constant(Signals),
+    Arithmetic {
+        first: Option<Operand>,
+        second: Operand,
+        operation: Operation,
+        output: Option<String>,
+    },
+    Decider {
+        first: Option<String>,
+        second: Operand,
+        comparator: Comparator,
+        output: Option<String>,
+        copy_count: bool,
+    },
+}
+
+#[derive(Debug, Clone)]
+struct Combinator {
+    entity: u32,
+    behavior: Behavior,
+    /// Signals the combinator currently outputs.
+    output: Signals,
+}
+
+/// Simulates the circuit networks of constant, arithmetic and decider combinators.
+///
+/// Like in Factorio, all combinators update at the same time once per tick: they read
+/// the networks at their input and the new outputs are visible on the networks in the
+/// next tick. Every combinator thus adds one tick of latency. All outputs connected to
+/// the same network are summed up, the input of a combinator is the sum of its red and
+/// green network. Other entities, e.g. power poles, only connect wires.
+#[derive(Debug, Clone)]
+pub struct Circuit {
+    combinators: Vec<Combinator>,
+    networks: Vec<Network>,
+    /// Network of every connection point with a wire of the given color.
+    network_of: HashMap<(u32, Point, WireColor), usize>,
+    /// Current signals of every network.
+    values: Vec<Signals>,
+    /// Number of simulated ticks.
+    pub ticks: u64,
+}
+
+impl Comparator {
+    fn from_symbol(symbol: &str) -> Option<Comparator> {
+        match symbol {
+            "<" => Some(Comparator::Less),
+            ">" => Some(Comparator::Greater),
+            "=" => Some(Comparator::Equal),
+            "≥" | ">=" => Some(Comparator::GreaterOrEqual),
+            "≤" | "<=" => Some(Comparator::LessOrEqual),
+            "≠" | "!=" => Some(Comparator::NotEqual),
+            _ => None,
+        }
+    }
+
+    fn apply(self, a: i32, b: i32) -> bool {
+        match self {
+            Comparator::Less => a < b,
+            Comparator::Greater => a > b,
+            Comparator::Equal => a == b,
+            Comparator::GreaterOrEqual => a >= b,
+            Comparator::LessOrEqual => a <= b,
+            Comparator::NotEqual => a != b,
+        }
+    }
+}
+
+impl Operand {
+    fn new(signal: &Option<SignalId>, constant: Option<i32>) -> Option<Operand> {
+        match (signal, constant) {
+            (Some(signal), _) => Some(Operand::Signal(signal.name.clone())),
+            (None, Some(constant)) => Some(Operand::Constant(constant)),
+            (None, None) => None,
+        }
+    }
+
+    fn value(&self, input: &Signals) -> i32 {
+        match self {
+            Operand::Signal(name) => input.get(name).copied().unwrap_or(0),
+            Operand::Constant(constant) => *constant,
+        }
+    }
+}
+
+impl Behavior {
+    /// Behavior of the combinator entity, `None` for all other entities.
+    fn new(entity: &Entity) -> Result<Option<Behavior>, String> {
+        let control = entity.control_behavior.clone().unwrap_or_default();
+        let behavior = match entity.name.as_str() {
+            "constant-combinator" => {
+                let mut signals = Signals::new();
+                if control.is_on != Some(false) {
+                    for filter in control.filters.iter().flatten() {
+                        add_signal(&mut signals, &filter.signal.name, filter.count);
+                    }
+                }
+                Behavior::Constant(signals)
+            }
+            "arithmetic-combinator" => {
+                let conditions = control.arithmetic_conditions.unwrap_or_else(|| ArithmeticConditions {
+                    operation: "*".into(),
+                    ..ArithmeticConditions::default()
+                });
+                Behavior::Arithmetic {
+                    first: Operand::new(&conditions.first_signal, conditions.first_constant),
+                    second: Operand::new(&conditions.second_signal, conditions.second_constant).unwrap_or(Operand::Constant(0)),
+                    operation: Operation::from_symbol(&conditions.operation).ok_or_else(|| format!("Unknown operation '{}' of entity {}", conditions.operation, entity.entity_number))?,
+                    output: conditions.output_signal.map(|signal| signal.name),
+                }
+            }
+            "decider-combinator" => {
+                let conditions = control.decider_conditions.unwrap_or_else(|| DeciderConditions {
+                    comparator: "<".into(),
+                    ..DeciderConditions::default()
+                });
+                Behavior::Decider {
+                    first: conditions.first_signal.map(|signal| signal.name),
+                    second: Operand::new(&conditions.second_signal, conditions.constant).unwrap_or(Operand::Constant(0)),
+                    comparator: Comparator::from_symbol(&conditions.comparator).ok_or_else(|| format!("Unknown comparator '{}' of entity {}", conditions.comparator, entity.entity_number))?,
+                    output: conditions.output_signal.map(|signal| signal.name),
+                    copy_count: conditions.copy_count_from_input,
+                }
+            }
+            _ => return Ok(None),
+        };
+        Ok(Some(behavior))
+    }
+
+    /// Connection points of the input and output.
+    fn points(&self) -> (Option<Point>, Point) {
+        match self {
+            Behavior::Constant(_) => (None, 1),
+            Behavior::Arithmetic { .. } | Behavior::Decider { .. } => (Some(1), 2),
+        }
+    }
+
+    /// Signals the combinator may output if the given signals may be present at its input.
+    fn possible_outputs(&self, input: &BTreeSet<String>) -> BTreeSet<String> {
+        let output = match self {
+            Behavior::Constant(signals) => return signals.keys().cloned().collect(),
+            Behavior::Arithmetic { output, .. } | Behavior::Decider { output, .. } => output.as_deref(),
+        };
+        match output {
+            Some(EACH) | Some(EVERYTHING) => input.clone(),
+            Some(ANYTHING) | None => BTreeSet::new(),
+            Some(output) => BTreeSet::from([output.to_string()]),
+        }
+    }
+
+    fn output(&self, input: &Signals) -> Signals {
+        let mut output = Signals::new();
+        match self {
+            Behavior::Constant(signals) => output = signals.clone(),
+            Behavior::Arithmetic { first, second, operation, output: output_signal } => {
+                let second = second.value(input);
+                match (first, output_signal.as_deref()) {
+                    (Some(Operand::Signal(first)), Some(EACH)) if first == EACH => {
+                        for (signal, &value) in input {
+                            add_signal(&mut output, signal, operation.apply(value, second));
+                        }
+                    }
+                    (Some(Operand::Signal(first)), Some(output_signal)) if first == EACH => {
+                        for &value in input.values() {
+                            add_signal(&mut output, output_signal, operation.apply(value, second));
+                        }
+                    }
+                    (Some(first), Some(output_signal)) if output_signal != EACH => {
+                        add_signal(&mut output, output_signal, operation.apply(first.value(input), second));
+                    }
+                    _ => {}
+                }
+            }
+            Behavior::Decider { first, second, comparator, output: output_signal, copy_count } => {
+                let second = second.value(input);
+                let passes = |value: i32| comparator.apply(value, second);
+                let count = |value: i32| if *copy_count { value } else { 1 };
+                let condition = match first.as_deref() {
+                    Some(EACH) => {
+                        let passing = input.iter().filter(|(_, &value)| passes(value));
+                        for (signal, &value) in passing {
+                            match output_signal.as_deref() {
+                                Some(EACH) | Some(EVERYTHING) => add_signal(&mut output, signal, count(value)),
+                                Some(output_signal) => add_signal(&mut output, output_signal, count(value)),
+                                None => {}
+                            }
+                        }
+                        return output;
+                    }
+                    Some(ANYTHING) => input.values().any(|&value| passes(value)),
+                    Some(EVERYTHING) => input.values().all(|&value| passes(value)),
+                    Some(signal) => passes(input.get(signal).copied().unwrap_or(0)),
+                    None => false,
+                };
+
+                if condition {
+                    match output_signal.as_deref() {
+                        Some(EVERYTHING) => {
+                            for (signal, &value) in input {
+                                add_signal(&mut output, signal, count(value));
+                            }
+                        }
+                        Some(EACH) | Some(ANYTHING) | None => {}
+                        Some(output_signal) => add_signal(&mut output, output_signal, count(input.get(output_signal).copied().unwrap_or(0))),
+                    }
+                }
+            }
+        }
+        output
+    }
+}
+
+/// Adds `value` to the signal, removing it if the sum is 0. Overflows wrap around.
+fn add_signal(signals: &mut Signals, signal: &str, value: i32) {
+    let sum = signals.get(signal).copied().unwrap_or(0).wrapping_add(value);
+    if sum == 0 {
+        signals.remove(signal);
+    } else {
+        signals.insert(signal.to_string(), sum);
+    }
+}
+
+fn find(parents: &mut [usize], node: usize) -> usize {
+    let mut root = node;
+    while parents[root] != root {
+        root = parents[root];
+    }
+    parents[node] = root;
+    root
+}
+
+impl Circuit {
+    /// Connects the combinators of the blueprint with its wires. Constant combinators
+    /// output their signals right away, all other combinators start without output.
+    pub fn new(blueprint: &Blueprint) -> Result<Circuit, String> {
+        let mut combinators = Vec::new();
+        for entity in &blueprint.entities {
+            if let Some(behavior) = Behavior::new(entity)? {
+                let output = match &behavior {
+                    Behavior::Constant(signals) => signals.clone(),
+                    _ => Signals::new(),
+                };
+                combinators.push(Combinator { entity: entity.entity_number, behavior, output });
+            }
+        }
+
+        // Every connection point with a wire is a node, wires join their nodes to networks.
+        let mut nodes = HashMap::new();
+        let mut parents = Vec::new();
+        let mut node = |key: (u32, Point, WireColor), parents: &mut Vec<usize>| {
+            *nodes.entry(key).or_insert_with(|| {
+                parents.push(parents.len());
+                parents.len() - 1
+            })
+        };
+        for entity in &blueprint.entities {
+            for (point, connection) in &entity.connections {
+                let point = point.parse::<Point>().map_err(|_| format!("Invalid connection point '{}' of entity {}", point, entity.entity_number))?;
+                for (color, wires) in [(WireColor::Red, &connection.red), (WireColor::Green, &connection.green)] {
+                    if wires.is_empty() {
+                        continue;
+                    }
+                    let from = node((entity.entity_number, point, color), &mut parents);
+                    for wire in wires {
+                        let to = node((wire.entity_id, wire.circuit_id.unwrap_or(1), color), &mut parents);
+                        let (from, to) = (find(&mut parents, from), find(&mut parents, to));
+                        parents[from] = to;
+                    }
+                }
+            }
+        }
+
+        // Networks are numbered in the order of their first connection point.
+        let mut nodes = nodes.into_iter().collect::<Vec<_>>();
+        nodes.sort_by_key(|&((entity, point, color), _)| (entity, point, color == WireColor::Green));
+        let mut roots = HashMap::new();
+        let mut networks: Vec<Network> = Vec::new();
+        let mut network_of = HashMap::new();
+        for ((entity, point, color), node) in nodes {
+            let root = find(&mut parents, node);
+            let count = roots.len();
+            let network = *roots.entry(root).or_insert(count);
+            if network == networks.len() {
+                networks.push(Network { color, points: Vec::new() });
+            }
+            networks[network].points.push((entity, point));
+            network_of.insert((entity, point, color), network);
+        }
+
+        let mut circuit = Circuit {
+            combinators,
+            values: vec![Signals::new(); networks.len()],
+            networks,
+            network_of,
+            ticks: 0,
+        };
+        circuit.update_networks();
+        Ok(circuit)
+    }
+
+    /// Simulates a single tick.
+    pub fn tick(&mut self) {
+        let outputs = self
+            .combinators
+            .iter()
+            .map(|combinator| combinator.behavior.output(&self.input_of(combinator)))
+            .collect::<Vec<_>>();
+        for (combinator, output) in self.combinators.iter_mut().zip(outputs) {
+            combinator.output = output;
+        }
+        self.update_networks();
+        self.ticks += 1;
+    }
+
+    pub fn run(&mut self, ticks: u64) {
+        for _ in 0..ticks {
+            self.tick();
+        }
+    }
+
+    /// Signals of the network at the connection point, empty if there is no wire.
+    pub fn network(&self, entity: u32, point: Point, color: WireColor) -> Signals {
+        match self.network_of.get(&(entity, point, color)) {
+            Some(&network) => self.values[network].clone(),
+            None => Signals::new(),
+        }
+    }
+
+    /// Sum of the red and green network at the connection point.
+    pub fn signals(&self, entity: u32, point: Point) -> Signals {
+        let mut signals = self.network(entity, point, WireColor::Red);
+        for (signal, value) in self.network(entity, point, WireColor::Green) {
+            add_signal(&mut signals, &signal, value);
+        }
+        signals
+    }
+
+    /// Signals the combinator currently outputs.
+    pub fn output(&self, entity: u32) -> Option<&Signals> {
+        self.combinators.iter().find(|combinator| combinator.entity == entity).map(|combinator| &combinator.output)
+    }
+
+    /// Changes the signals of a constant combinator, e.g. to drive the inputs of a circuit.
+    /// The signals are visible on its network immediately.
+    pub fn set_constant(&mut self, entity: u32, signals: Signals) {
+        let combinator = self.combinators.iter_mut().find(|combinator| combinator.entity == entity);
+        match combinator {
+            Some(Combinator { behavior: Behavior::Constant(constant), output, .. }) => {
+                *output = signals.clone();
+                *constant = signals;
+            }
+            _ => panic!("Entity {} is not a constant combinator", entity),
+        }
+        self.update_networks();
+    }
+
+    pub fn networks(&self) -> &[Network] {
+        &self.networks
+    }
+
+    /// Current signals of the network with the given index in `networks`.
+    pub fn values(&self, network: usize) -> &Signals {
+        &self.values[network]
+    }
+
+    /// Signals which may ever be present on each network (indexed like `networks`),
+    /// independent of the actual values. Outputs of `each` and `everything` may
+    /// carry every signal which may be present at the input.
+    pub fn possible_signals(&self) -> Vec<BTreeSet<String>> {
+        let mut possible = vec![BTreeSet::new(); self.networks.len()];
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for combinator in &self.combinators {
+                let (input, output) = combinator.behavior.points();
+                let mut inputs = BTreeSet::new();
+                for color in [WireColor::Red, WireColor::Green] {
+                    if let Some(&network) = input.and_then(|input| self.network_of.get(&(combinator.entity, input, color))) {
+                        inputs.extend(possible[network].iter().cloned());
+                    }
+                }
+                let outputs = combinator.behavior.possible_outputs(&inputs);
+                for color in [WireColor::Red, WireColor::Green] {
+                    if let Some(&network) = self.network_of.get(&(combinator.entity, output, color)) {
+                        let before = possible[network].len();
+                        possible[network].extend(outputs.iter().cloned());
+                        changed |= possible[network].len() != before;
+                    }
+                }
+            }
+        }
+        possible
+    }
+
+    /// Entity numbers of all combinators.
+    pub fn combinators(&self) -> impl Iterator<Item = u32> + '_ {
+        self.combinators.iter().map(|combinator| combinator.entity)
+    }
+
+    fn input_of(&self, combinator: &Combinator) -> Signals {
+        match combinator.behavior.points().0 {
+            Some(point) => self.signals(combinator.entity, point),
+            None => Signals::new(),
+        }
+    }
+
+    fn update_networks(&mut self) {
+        for values in &mut self.values {
+            values.clear();
+        }
+        for combinator in &self.combinators {
+            let point = combinator.behavior.points().1;
+            for color in [WireColor::Red, WireColor::Green] {
+                if let Some(&network) = self.network_of.get(&(combinator.entity, point, color)) {
+                    for (signal, &value) in &combinator.output {
+                        add_signal(&mut self.values[network], signal, value);
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,81 @@
+use crate::rom::RomPlan;
+
+/// Prefix of Factorio console commands which run Lua code.
+pub const COMMAND_PREFIX: &str = "/c ";
+
+/// Start of the output of the console command if it has written the program.
+pub const LOADED_PREFIX: &str = "Loaded ";
+
+/// First combinator of the ROM the console command writes to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Anchor {
+    /// Constant combinator under the cursor of the player running the command. Not available over RCON.
+    Player,
+    /// Map tag with the given text, placed on the first combinator.
+    Tag(String),
+}
+
+/// Console command which writes the program into the ROM of a running game. The ROM is a row of
+/// constant combinators from the anchor to the right, which hold the words in their slots like the
+/// blueprint of the same `RomPlan`. Only the combinators at these positions are changed. If one of
+/// them is missing, the command fails before it changes anything. Combinators after the end of the
+/// program keep their content.
+///
+/// The command is a single line, so it can be pasted into the console or sent over RCON.
+pub fn rom_loader(rom: &RomPlan, anchor: &Anchor) -> String {
+    let rows = rom
+        .combinators
+        .iter()
+        .map(|words| format!("{{{}}}", words.iter().map(i32::to_string).collect::<Vec<_>>().join(", ")))
+        .collect::<Vec<_>>();
+    let signals = rom.selector().signals.iter().map(|signal| lua_string(signal)).collect::<Vec<_>>();
+    let origin = match anchor {
+        Anchor::Player => "local first = game.player and game.player.selected \
+             if not first or first.name ~= \"constant-combinator\" then error(\"Hover over the first rom combinator, or use a map tag\", 0) end \
+             local origin = first.position"
+            .to_string(),
+        Anchor::Tag(text) => format!(
+            "local tag_text = {} local origin for _, tag in pairs(force.find_chart_tags(surface)) do if tag.text == tag_text then origin = tag.position end end \
+             if not origin then error(\"Map tag \" .. tag_text .. \" not found\", 0) end \
+             origin = {{x = math.floor(origin.x) + 0.5, y = math.floor(origin.y) + 0.5}}",
+            lua_string(text),
+        ),
+    };
+    let statements = [
+        format!("local rows = {{{}}}", rows.join(", ")),
+        format!("local signals = {{{}}}", signals.join(", ")),
+        "local function report(message) if game.player then game.player.print(message) else rcon.print(message) end end".into(),
+        "local surface = game.player and game.player.surface or game.surfaces[1]".into(),
+        "local force = game.player and game.player.force or game.forces.player".into(),
+        origin,
+        // Find every combinator before writing to any of them.
+        "local roms = {} for i = 1, #rows do local position = {x = origin.x + i - 1, y = origin.y} \
+         roms[i] = surface.find_entities_filtered{name = \"constant-combinator\", force = force, position = position}[1] \
+         if not roms[i] then error(\"Found \" .. (i - 1) .. \" rom combinators, but the program needs \" .. #rows .. \": no constant combinator at \" .. position.x .. \", \" .. position.y, 0) end end"
+            .into(),
+        // Slots without a word are cleared, also those after the slots of the rom.
+        "for i, rom in ipairs(roms) do local behavior = rom.get_or_create_control_behavior() for slot = 1, behavior.signals_count do local word = rows[i][slot] \
+         behavior.set_signal(slot, word and word ~= 0 and {signal = {type = \"virtual\", name = signals[slot]}, count = word} or nil) end end"
+            .into(),
+        format!("report(\"{}{} words into \" .. #roms .. \" rom combinators\")", LOADED_PREFIX, rom.words().len()),
+    ];
+    format!("{}{}", COMMAND_PREFIX, statements.join(" "))
+}
+
+/// Quoted Lua string literal.
+fn lua_string(text: &str) -> String {
+    let mut literal = String::from("\"");
+    for c in text.chars() {
+        match c {
+            '"' | '\\' => {
+                literal.push('\\');
+                literal.push(c);
+            }
+            '\n' => literal.push_str("\\n"),
+            '\r' => literal.push_str("\\r"),
+            c => literal.push(c),
+        }
+    }
+    literal.push('"');
+    literal
+}
@@ -0,0 +1,77 @@
+use std::{fs, io, path::Path};
+
+use serde::{Deserialize, Serialize};
+
+/// Maps word addresses of an assembled program back to its source.
+///
+/// All addresses are absolute word addresses, i.e. the value IP has
+/// while the corresponding instruction is executed.
+#[derive(Serialize, Deserialize, Default, Debug, Clone, PartialEq)]
+pub struct DebugInfo {
+    /// Source files referenced by `LineInfo::file`.
+    pub files: Vec<String>,
+    pub symbols: Vec<Symbol>,
+    pub lines: Vec<LineInfo>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Symbol {
+    pub name: String,
+    pub address: u32,
+}
+
+/// Source location of a single emitted instruction or data directive.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct LineInfo {
+    pub file: usize,
+    pub line: usize,
+    /// First word of the instruction.
+    pub start: u32,
+    /// One past the last word of the instruction.
+    pub end: u32,
+    /// The words were emitted by `.word` or `.space` instead of an instruction.
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub data: bool,
+}
+
+fn is_false(value: &bool) -> bool {
+    !value
+}
+
+impl DebugInfo {
+    pub fn read<P: AsRef<Path>>(path: P) -> io::Result<DebugInfo> {
+        let content = fs::read_to_string(path)?;
+        serde_json::from_str(&content).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+
+    pub fn write<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let content = serde_json::to_string_pretty(self).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        fs::write(path, content)
+    }
+
+    /// Address of the symbol with the given name.
+    pub fn symbol(&self, name: &str) -> Option<u32> {
+        self.symbols.iter().find(|symbol| symbol.name == name).map(|symbol| symbol.address)
+    }
+
+    /// Closest symbol at or before `address`. This is usually the function
+    /// or block the address belongs to.
+    pub fn symbol_before(&self, address: u32) -> Option<&Symbol> {
+        self.symbols
+            .iter()
+            .filter(|symbol| symbol.address <= address)
+            .max_by_key(|symbol| symbol.address)
+    }
+
+    /// Source location of the instruction covering `address`.
+    pub fn line_at(&self, address: u32) -> Option<&LineInfo> {
+        self.lines.iter().find(|line| line.start <= address && address < line.end)
+    }
+
+    /// Human readable `file:line` of the instruction covering `address`.
+    pub fn location(&self, address: u32) -> Option<String> {
+        let line = self.line_at(address)?;
+        let file = self.files.get(line.file).map(String::as_str).unwrap_or("?");
+        Some(format!("{}:{}", file, line.line))
+    }
+}
@@ -0,0 +1,297 @@
+use std::{collections::{BTreeSet, HashMap}, fmt::Write, fs};
+
+use crate::{
+    debug_info::DebugInfo,
+    disassembler::{Decoder, Instruction},
+    emulator::{Emulator, Step},
+    ir::{parse_number, IRCommand, IRRegister},
+    Program,
+};
+
+/// Instructions executed by a single command before the debugger gives
+/// control back, so an endless loop does not lock up the debugger.
+const MAX_RESUME_STEPS: u64 = 10_000_000;
+
+/// Instructions shown before and after IP in the disassembly view.
+const DISASSEMBLY_CONTEXT: usize = 4;
+
+const HELP: &str = "\
+Commands:
+  step, s                 Execute a single instruction
+  next, n                 Execute a single instruction, stepping over CALL and INT
+  finish, f               Execute until the current function returns
+  continue, c             Execute until a breakpoint, watchpoint or HALT
+  break, b LOCATION       Set a breakpoint at a label, LINE, FILE:LINE or *ADDRESS
+  watch, w ADDRESS        Stop after writes to ADDRESS (number or label)
+  delete, d [LOCATION]    Remove a breakpoint, or all breakpoints and watchpoints
+  breakpoints             List breakpoints and watchpoints
+  registers, r            Show registers and flags
+  x ADDRESS [COUNT]       Show memory at ADDRESS (number or label)
+  disassemble, disas      Show the instructions around IP
+  where                   Show the current location
+  quit, q                 Exit the debugger
+An empty line repeats the last command.";
+
+/// Source-level debugger driving an emulator with text commands.
+pub struct Debugger {
+    emulator: Emulator,
+    debug_info: DebugInfo,
+    /// Addresses of the loaded program, used for the disassembly view.
+    rom: (u32, u32),
+    decoder: Decoder,
+    breakpoints: BTreeSet<i32>,
+    watchpoints: BTreeSet<i32>,
+    /// Lines of the source files, loaded on first use.
+    sources: HashMap<usize, Option<Vec<String>>>,
+    last_command: String,
+}
+
+impl Debugger {
+    /// Debugs `program` in the given emulator, which should already have the program loaded.
+    pub fn new(emulator: Emulator, program: &Program) -> Debugger {
+        Debugger {
+            emulator,
+            debug_info: program.debug_info.clone(),
+            rom: (program.start, program.start + program.binary.len() as u32 / 4),
+            decoder: Decoder::new(),
+            breakpoints: BTreeSet::new(),
+            watchpoints: BTreeSet::new(),
+            sources: HashMap::new(),
+            last_command: String::new(),
+        }
+    }
+
+    pub fn emulator(&self) -> &Emulator {
+        &self.emulator
+    }
+
+    /// Executes a single command and returns its output, or `None` if the debugger should exit.
+    pub fn execute(&mut self, line: &str) -> Option<String> {
+        let line = match line.trim() {
+            "" => self.last_command.clone(),
+            line => line.to_string(),
+        };
+        self.last_command = line.clone();
+
+        let mut words = line.split_whitespace();
+        let command = words.next().unwrap_or("");
+        let argument = words.next();
+        let output = match (command, argument) {
+            ("", _) => String::new(),
+            ("step" | "s", None) => self.resume(|_, _| true),
+            ("next" | "n", None) => self.next(),
+            ("finish" | "f", None) => self.finish(),
+            ("continue" | "c", None) => self.resume(|_, _| false),
+            ("break" | "b", Some(location)) => match self.resolve_location(location) {
+                Ok(address) => {
+                    self.breakpoints.insert(address);
+                    format!("Breakpoint at {}", self.describe(address))
+                }
+                Err(e) => e,
+            },
+            ("watch" | "w", Some(address)) => match self.resolve_address(address) {
+                Ok(address) => {
+                    self.watchpoints.insert(address);
+                    format!("Watchpoint at [0x{:04x}]", address)
+                }
+                Err(e) => e,
+            },
+            ("delete" | "d", None) => {
+                self.breakpoints.clear();
+                self.watchpoints.clear();
+                "Deleted all breakpoints and watchpoints".into()
+            }
+            ("delete" | "d", Some(location)) => match self.resolve_location(location) {
+                Ok(address) if self.breakpoints.remove(&address) || self.watchpoints.remove(&address) => {
+                    format!("Deleted {}", self.describe(address))
+                }
+                Ok(address) => format!("No breakpoint at {}", self.describe(address)),
+                Err(e) => e,
+            },
+            ("breakpoints", None) => self.list_breakpoints(),
+            ("registers" | "r", None) => self.emulator.to_string(),
+            ("x", Some(address)) => {
+                let count = words.next().map_or(Some(1), parse_number);
+                match (self.resolve_address(address), count) {
+                    (Ok(address), Some(count)) => self.memory(address, count),
+                    (Err(e), _) => e,
+                    (_, None) => "Invalid count".into(),
+                }
+            }
+            ("disassemble" | "disas", None) => self.disassembly(),
+            ("where", None) => self.location(),
+            ("quit" | "q", None) => return None,
+            ("help" | "h", None) => HELP.into(),
+            _ => format!("Invalid command '{}'. Try 'help'.", line),
+        };
+
+        Some(output)
+    }
+
+    /// Executes instructions until `until` returns true for an executed step, or the
+    /// program stops for another reason. Returns a description of why execution stopped.
+    fn resume(&mut self, mut until: impl FnMut(&Emulator, &Step) -> bool) -> String {
+        for _ in 0..MAX_RESUME_STEPS {
+            let step = match self.emulator.step() {
+                Ok(Some(step)) => step,
+                Ok(None) => return "The program has halted".into(),
+                Err(fault) => return format!("{}\n{}", fault, self.location()),
+            };
+
+            if self.emulator.is_halted() {
+                let steps = self.emulator.steps;
+                return format!("Halted after {} instructions\n{}", steps, self.location());
+            }
+            if let Some((address, value)) = step.writes.iter().find(|(address, _)| self.watchpoints.contains(address)) {
+                return format!("Watchpoint [0x{:04x}] = {}\n{}", address, value, self.location());
+            }
+            if until(&self.emulator, &step) {
+                return self.location();
+            }
+            if self.breakpoints.contains(&self.emulator.register(IRRegister::IP)) {
+                return format!("Breakpoint\n{}", self.location());
+            }
+        }
+
+        format!("Paused after {} instructions\n{}", MAX_RESUME_STEPS, self.location())
+    }
+
+    /// Steps over CALL and INT by executing until the called function returned.
+    fn next(&mut self) -> String {
+        let instruction = match self.emulator.current_instruction() {
+            Ok(instruction) if matches!(instruction.command, IRCommand::Call | IRCommand::Int) => instruction,
+            _ => return self.resume(|_, _| true),
+        };
+
+        let return_address = self.emulator.register(IRRegister::IP) + instruction.size as i32;
+        let sp = self.emulator.register(IRRegister::SP);
+        self.resume(|emulator, _| emulator.register(IRRegister::IP) == return_address && emulator.register(IRRegister::SP) == sp)
+    }
+
+    /// Executes until a RET removes the return address of the current function from the stack.
+    fn finish(&mut self) -> String {
+        let sp = self.emulator.register(IRRegister::SP);
+        self.resume(|emulator, step| step.instruction.command == IRCommand::Ret && emulator.register(IRRegister::SP) > sp)
+    }
+
+    /// Resolves a breakpoint location: a label, `LINE` (in any file), `FILE:LINE` or `*ADDRESS`.
+    fn resolve_location(&self, location: &str) -> Result<i32, String> {
+        if let Some(address) = location.strip_prefix('*') {
+            return parse_number(address).ok_or_else(|| format!("Invalid address '{}'", address));
+        }
+
+        let (file, line) = match location.rsplit_once(':') {
+            Some((file, line)) => (Some(file), line),
+            None => (None, location),
+        };
+        let line = match line.parse::<usize>() {
+            Ok(line) => line,
+            Err(_) if file.is_none() => return self.resolve_address(location),
+            Err(_) => return Err(format!("Invalid line '{}'", line)),
+        };
+
+        self.debug_info
+            .lines
+            .iter()
+            .filter(|info| info.line == line)
+            .filter(|info| file.is_none_or(|file| self.debug_info.files.get(info.file).is_some_and(|name| name.ends_with(file))))
+            .map(|info| info.start as i32)
+            .min()
+            .ok_or_else(|| format!("No instruction on line '{}'", location))
+    }
+
+    /// Resolves a memory address given as number or label.
+    fn resolve_address(&self, address: &str) -> Result<i32, String> {
+        parse_number(address)
+            .or_else(|| self.debug_info.symbol(address).map(|address| address as i32))
+            .ok_or_else(|| format!("Unknown address or label '{}'", address))
+    }
+
+    /// Address with the closest label before it, e.g. `0x0006 <loop+2>`.
+    fn describe(&self, address: i32) -> String {
+        match self.debug_info.symbol_before(address as u32) {
+            Some(symbol) if symbol.address == address as u32 => format!("0x{:04x} <{}>", address, symbol.name),
+            Some(symbol) => format!("0x{:04x} <{}+{}>", address, symbol.name, address as u32 - symbol.address),
+            None => format!("0x{:04x}", address),
+        }
+    }
+
+    /// Current address with its source line, or the instruction if there is no source.
+    fn location(&mut self) -> String {
+        let ip = self.emulator.register(IRRegister::IP);
+        let description = self.describe(ip);
+        let line = self.debug_info.line_at(ip as u32).cloned();
+        let source = line.as_ref().and_then(|line| self.source_line(line.file, line.line));
+
+        match (line, source) {
+            (Some(line), Some(source)) => format!("{} at {}\n{:>5} | {}", description, self.debug_info.location(line.start).unwrap(), line.line, source.trim_end()),
+            _ => match self.emulator.current_instruction() {
+                Ok(instruction) => format!("{}: {}", description, self.format_instruction(ip, &instruction)),
+                Err(_) => format!("{}: .word 0x{:08x}", description, self.emulator.read(ip) as u32),
+            },
+        }
+    }
+
+    fn source_line(&mut self, file: usize, line: usize) -> Option<String> {
+        let debug_info = &self.debug_info;
+        let lines = self.sources.entry(file).or_insert_with(|| {
+            let content = fs::read_to_string(debug_info.files.get(file)?).ok()?;
+            Some(content.lines().map(String::from).collect())
+        });
+        lines.as_ref()?.get(line.checked_sub(1)?).cloned()
+    }
+
+    /// Jump targets are shown with their label.
+    fn format_instruction(&self, address: i32, instruction: &Instruction) -> String {
+        match instruction.target(address as u32) {
+            Some(target) => format!("{} {}", instruction.command.mnemonic(), self.describe(target as i32)),
+            None => instruction.to_string(),
+        }
+    }
+
+    fn list_breakpoints(&self) -> String {
+        let breakpoints = self.breakpoints.iter().map(|&address| format!("Breakpoint at {}", self.describe(address)));
+        let watchpoints = self.watchpoints.iter().map(|address| format!("Watchpoint at [0x{:04x}]", address));
+        let list = breakpoints.chain(watchpoints).collect::<Vec<_>>();
+        if list.is_empty() {
+            "No breakpoints or watchpoints".into()
+        } else {
+            list.join("\n")
+        }
+    }
+
+    fn memory(&self, address: i32, count: i32) -> String {
+        (address..address.saturating_add(count))
+            .map(|address| match self.debug_info.symbols.iter().find(|symbol| symbol.address as i32 == address) {
+                Some(symbol) => format!("0x{:04x} <{}>: {}", address, symbol.name, self.emulator.read(address)),
+                None => format!("0x{:04x}: {}", address, self.emulator.read(address)),
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    /// Instructions of the program around IP. Decoded from memory, so changes
+    /// of self-modifying code are visible.
+    fn disassembly(&self) -> String {
+        let (start, end) = self.rom;
+        let ip = self.emulator.register(IRRegister::IP);
+        let words = (start..end).map(|address| self.emulator.read(address as i32) as u32).collect::<Vec<_>>();
+        let decoded = self.decoder.sweep(&words, start);
+        let current = decoded.iter().position(|&(address, _)| address as i32 >= ip).unwrap_or(decoded.len());
+
+        let mut output = String::new();
+        for (address, instruction) in &decoded[current.saturating_sub(DISASSEMBLY_CONTEXT)..(current + DISASSEMBLY_CONTEXT + 1).min(decoded.len())] {
+            let address = *address as i32;
+            if let Some(symbol) = self.debug_info.symbols.iter().find(|symbol| symbol.address as i32 == address) {
+                writeln!(output, "{}:", symbol.name).unwrap();
+            }
+            let text = match instruction {
+                Some(instruction) => self.format_instruction(address, instruction),
+                None => format!(".word 0x{:08x}", self.emulator.read(address) as u32),
+            };
+            let marker = if address == ip { "=>" } else { "  " };
+            writeln!(output, "{} 0x{:04x}  {}", marker, address, text).unwrap();
+        }
+        output.trim_end().into()
+    }
+}
@@ -0,0 +1,206 @@
+use std::{
+    collections::VecDeque,
+    fs::File,
+    io::{self, Read},
+};
+
+use crate::ir::parse_number;
+
+/// External component connected to addresses outside of the memory.
+///
+/// Offsets are relative to the address the device is attached to.
+pub trait Device {
+    /// Number of addresses used by the device.
+    fn size(&self) -> u32;
+
+    fn read(&mut self, offset: u32) -> i32;
+
+    fn write(&mut self, offset: u32, value: i32);
+
+    /// Called after every executed instruction with the number of ticks it took.
+    fn tick(&mut self, _ticks: u64) {}
+
+    /// Text representation of the device state, printed after a run.
+    fn render(&self) -> Option<String> {
+        None
+    }
+}
+
+/// Grid of lamps, one word per row. Bit `x` of a row controls the lamp in column `x`.
+pub struct LampMatrix {
+    width: u32,
+    rows: Vec<i32>,
+}
+
+impl LampMatrix {
+    pub fn new(width: u32, height: u32) -> LampMatrix {
+        assert!(width <= 32, "Lamp matrix rows have at most 32 lamps");
+        LampMatrix {
+            width,
+            rows: vec![0; height as usize],
+        }
+    }
+}
+
+impl Device for LampMatrix {
+    fn size(&self) -> u32 {
+        self.rows.len() as u32
+    }
+
+    fn read(&mut self, offset: u32) -> i32 {
+        self.rows[offset as usize]
+    }
+
+    fn write(&mut self, offset: u32, value: i32) {
+        self.rows[offset as usize] = value;
+    }
+
+    fn render(&self) -> Option<String> {
+        let rows = self.rows.iter().map(|row| (0..self.width).map(|x| if row >> x & 1 == 1 { '#' } else { '.' }).collect::<String>());
+        Some(rows.collect::<Vec<_>>().join("\n"))
+    }
+}
+
+/// Shows the last written number.
+#[derive(Default)]
+pub struct NumericDisplay {
+    value: i32,
+}
+
+impl Device for NumericDisplay {
+    fn size(&self) -> u32 {
+        1
+    }
+
+    fn read(&mut self, _offset: u32) -> i32 {
+        self.value
+    }
+
+    fn write(&mut self, _offset: u32, value: i32) {
+        self.value = value;
+    }
+
+    fn render(&self) -> Option<String> {
+        Some(self.value.to_string())
+    }
+}
+
+/// Input queue. Every read takes the next byte from the queue,
+/// or the next byte from the input source once the queue is empty.
+/// Reads 0 (no signal) when there is no more input.
+pub struct Keyboard {
+    queue: VecDeque<i32>,
+    source: Option<Box<dyn Read>>,
+}
+
+impl Keyboard {
+    /// Keyboard which is fed from stdin.
+    pub fn stdin() -> Keyboard {
+        Keyboard::from_reader(io::stdin())
+    }
+
+    /// Keyboard which is fed from the content of a script file.
+    pub fn from_file(path: &str) -> io::Result<Keyboard> {
+        Ok(Keyboard::from_reader(File::open(path)?))
+    }
+
+    pub fn from_reader(source: impl Read + 'static) -> Keyboard {
+        Keyboard {
+            queue: VecDeque::new(),
+            source: Some(Box::new(source)),
+        }
+    }
+
+    pub fn from_values(values: impl IntoIterator<Item = i32>) -> Keyboard {
+        Keyboard {
+            queue: values.into_iter().collect(),
+            source: None,
+        }
+    }
+}
+
+impl Device for Keyboard {
+    fn size(&self) -> u32 {
+        1
+    }
+
+    fn read(&mut self, _offset: u32) -> i32 {
+        if let Some(value) = self.queue.pop_front() {
+            return value;
+        }
+
+        let mut byte = [0];
+        match self.source.as_mut().map(|source| source.read(&mut byte)) {
+            Some(Ok(1)) => byte[0].into(),
+            _ => 0,
+        }
+    }
+
+    /// Writes put a value back at the front of the queue.
+    fn write(&mut self, _offset: u32, value: i32) {
+        self.queue.push_front(value);
+    }
+}
+
+/// Counts ticks since it was attached or last written.
+#[derive(Default)]
+pub struct Timer {
+    ticks: u64,
+}
+
+impl Device for Timer {
+    fn size(&self) -> u32 {
+        1
+    }
+
+    fn read(&mut self, _offset: u32) -> i32 {
+        self.ticks as i32
+    }
+
+    /// Sets the counter to the written value.
+    fn write(&mut self, _offset: u32, value: i32) {
+        self.ticks = value as u64;
+    }
+
+    fn tick(&mut self, ticks: u64) {
+        self.ticks += ticks;
+    }
+}
+
+/// Creates a device from a command line description `KIND@ADDRESS[:OPTIONS]`:
+///
+/// * `lamps@ADDRESS:WIDTHxHEIGHT` - [`LampMatrix`]
+/// * `number@ADDRESS` - [`NumericDisplay`]
+/// * `keyboard@ADDRESS[:FILE]` - [`Keyboard`] fed from the file or stdin
+/// * `timer@ADDRESS` - [`Timer`]
+pub fn parse_device(description: &str) -> Result<(i32, Box<dyn Device>), String> {
+    let (kind, location) = description.split_once('@').ok_or_else(|| format!("Device '{}' has no address", description))?;
+    let (address, options) = match location.split_once(':') {
+        Some((address, options)) => (address, Some(options)),
+        None => (location, None),
+    };
+    let address = parse_number(address).ok_or_else(|| format!("Invalid device address '{}'", address))?;
+
+    let device: Box<dyn Device> = match (kind, options) {
+        ("lamps", Some(size)) => {
+            let (width, height) = size
+                .split_once('x')
+                .and_then(|(width, height)| Some((width.parse().ok()?, height.parse().ok()?)))
+                .filter(|&(width, height)| width <= 32 && height > 0)
+                .ok_or_else(|| format!("Invalid lamp matrix size '{}'", size))?;
+            Box::new(LampMatrix::new(width, height))
+        }
+        ("number", None) => Box::new(NumericDisplay::default()),
+        ("keyboard", None) => Box::new(Keyboard::stdin()),
+        ("keyboard", Some(file)) => Box::new(Keyboard::from_file(file).map_err(|e| format!("Could not read '{}': {}", file, e))?),
+        ("timer", None) => Box::new(Timer::default()),
+        _ => return Err(format!("Unknown device '{}'", description)),
+    };
+
+    Ok((address, device))
+}
+
+/// Whether the device of a description reads from stdin: a keyboard without a file.
+pub fn reads_stdin(description: &str) -> bool {
+    matches!(description.split_once('@'), Some(("keyboard", location)) if !location.contains(':'))
+}
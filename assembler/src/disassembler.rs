@@ -0,0 +1,213 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::{self, Write},
+};
+
+use crate::{
+    assembler::{AssemblyTranslation, InstructionSignature},
+    debug_info::DebugInfo,
+    ir::{IRCommand, IRParamType, IRRegister},
+};
+
+/// A decoded instruction.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Instruction {
+    /// Instruction type in the lowest byte of the first word.
+    pub opcode: u8,
+    pub command: IRCommand,
+    pub operands: Vec<Operand>,
+    /// Size in words, including the additional values.
+    pub size: u32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Operand {
+    Register(IRRegister),
+    Immediate(i32),
+    MemoryAtRegister(IRRegister),
+    MemoryAtImmediate(i32),
+    /// Jump location relative to the instruction.
+    Location(i32),
+}
+
+/// Decodes instruction words using the encoding table of the assembler.
+pub struct Decoder {
+    table: HashMap<u8, (InstructionSignature, bool)>,
+}
+
+impl Decoder {
+    pub fn new() -> Decoder {
+        Decoder {
+            table: AssemblyTranslation::new().decoding_table(),
+        }
+    }
+
+    /// Decodes the instruction at the start of `words`. Returns `None` if the
+    /// words are not an instruction which could have been produced by the assembler.
+    pub fn decode(&self, words: &[u32]) -> Option<Instruction> {
+        let first = *words.first()?;
+        let bytes = first.to_be_bytes();
+        let ((command, param1, param2), byte_immediates) = self.table.get(&bytes[3])?;
+
+        let mut operands = Vec::new();
+        let mut next_register = 2;
+        let mut size = 1;
+        for param_type in [param1, param2] {
+            let operand = match param_type {
+                IRParamType::None => continue,
+                IRParamType::Register | IRParamType::MemoryAtRegister => {
+                    let register = IRRegister::from_encoding(bytes[next_register])?;
+                    next_register -= 1;
+                    match param_type {
+                        IRParamType::Register => Operand::Register(register),
+                        _ => Operand::MemoryAtRegister(register),
+                    }
+                }
+                IRParamType::Immediate if *byte_immediates => {
+                    let value = bytes[next_register];
+                    next_register -= 1;
+                    Operand::Immediate(value.into())
+                }
+                IRParamType::Immediate | IRParamType::MemoryAtImmediate => {
+                    let value = *words.get(size as usize)? as i32;
+                    size += 1;
+                    match param_type {
+                        IRParamType::Immediate => Operand::Immediate(value),
+                        _ => Operand::MemoryAtImmediate(value),
+                    }
+                }
+                // Sign retaining shift of the i24 location.
+                IRParamType::Label => Operand::Location((first as i32) >> 8),
+            };
+            operands.push(operand);
+        }
+
+        // The assembler pads unused bytes with 0s.
+        if *param1 != IRParamType::Label && bytes[..=next_register].iter().any(|&byte| byte != 0) {
+            return None;
+        }
+
+        Some(Instruction {
+            opcode: bytes[3],
+            command: command.clone(),
+            operands,
+            size,
+        })
+    }
+
+    /// Decodes `words` (starting at address `start`) one instruction after another.
+    /// Words which are not instructions are returned as `None` and skipped.
+    pub fn sweep(&self, words: &[u32], start: u32) -> Vec<(u32, Option<Instruction>)> {
+        let mut decoded = Vec::new();
+        let mut index = 0;
+        while index < words.len() {
+            let instruction = self.decode(&words[index..]);
+            let size = instruction.as_ref().map_or(1, |instruction| instruction.size as usize);
+            decoded.push((start + index as u32, instruction));
+            index += size;
+        }
+        decoded
+    }
+}
+
+impl Default for Decoder {
+    fn default() -> Decoder {
+        Decoder::new()
+    }
+}
+
+impl Instruction {
+    /// Absolute target of a jump or call located at `address`.
+    pub fn target(&self, address: u32) -> Option<u32> {
+        self.operands.iter().find_map(|operand| match operand {
+            Operand::Location(location) => address.checked_add_signed(*location),
+            _ => None,
+        })
+    }
+}
+
+impl fmt::Display for Instruction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.command.mnemonic())?;
+        for (i, operand) in self.operands.iter().enumerate() {
+            write!(f, "{}{}", if i == 0 { " " } else { ", " }, operand)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Operand {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Operand::Register(register) => write!(f, "{}", register.name()),
+            Operand::Immediate(value) => write!(f, "{}", value),
+            Operand::MemoryAtRegister(register) => write!(f, "[{}]", register.name()),
+            Operand::MemoryAtImmediate(address) => write!(f, "[0x{:x}]", address),
+            Operand::Location(location) => write!(f, "{:+}", location),
+        }
+    }
+}
+
+/// Turns a binary back into assembly, which assembles to the same binary.
+///
+/// Jump and call targets get synthetic labels named after their address, or
+/// the symbol names from `debug_info` if available. Words that are not valid
+/// instructions are written as `.word`.
+pub fn disassemble(binary: &[u8], start: u32, debug_info: Option<&DebugInfo>) -> String {
+    if !binary.len().is_multiple_of(4) {
+        eprintln!("[Warning] Binary size is not a multiple of 4 bytes, ignoring the last {} byte(s)", binary.len() % 4);
+    }
+
+    let decoder = Decoder::new();
+    let words = binary.chunks_exact(4).map(|word| u32::from_be_bytes(word.try_into().unwrap())).collect::<Vec<_>>();
+
+    let decoded = decoder.sweep(&words, start).into_iter().map(|(address, instruction)| {
+        let index = (address - start) as usize;
+        let size = instruction.as_ref().map_or(1, |instruction| instruction.size as usize);
+        (address, instruction, &words[index..index + size])
+    }).collect::<Vec<_>>();
+
+    // Labels can only be placed in front of instructions.
+    let boundaries = decoded.iter().map(|(address, _, _)| *address).collect::<HashSet<_>>();
+    let symbol_at = |address: u32| {
+        debug_info
+            .and_then(|debug_info| debug_info.symbols.iter().find(|symbol| symbol.address == address))
+            .map(|symbol| symbol.name.clone())
+    };
+
+    let mut labels = HashMap::new();
+    for (address, instruction, _) in &decoded {
+        if let Some(target) = instruction.as_ref().and_then(|instruction| instruction.target(*address)) {
+            if boundaries.contains(&target) {
+                labels.entry(target).or_insert_with(|| symbol_at(target).unwrap_or_else(|| format!("L{:04x}", target)));
+            }
+        }
+        if let Some(name) = symbol_at(*address) {
+            labels.entry(*address).or_insert(name);
+        }
+    }
+
+    let mut output = String::new();
+    for (address, instruction, instruction_words) in decoded {
+        if let Some(label) = labels.get(&address) {
+            writeln!(output, "{}:", label).unwrap();
+        }
+
+        let text = match instruction {
+            Some(instruction) => match instruction.target(address) {
+                Some(target) => match labels.get(&target) {
+                    Some(label) => format!("{} {}", instruction.command.mnemonic(), label),
+                    None => format!(".word 0x{:08x}", instruction_words[0]),
+                },
+                None if instruction.command.is_relative_jump() => format!(".word 0x{:08x}", instruction_words[0]),
+                None => instruction.to_string(),
+            },
+            None => format!(".word 0x{:08x}", instruction_words[0]),
+        };
+
+        let hex = instruction_words.iter().map(|word| format!("{:08x}", word)).collect::<Vec<_>>().join(" ");
+        writeln!(output, "    {:<24}; {:04x}: {}", text, address, hex).unwrap();
+    }
+
+    output
+}
@@ -0,0 +1,502 @@
+use std::{
+    collections::HashMap,
+    fmt,
+    ops::{Range, RangeInclusive},
+};
+
+use crate::{
+    arithmetic::Operation,
+    device::Device,
+    disassembler::{Decoder, Instruction, Operand},
+    ir::{IRCommand, IRRegister},
+    timing::TimingModel,
+    Program, PROGRAM_START,
+};
+
+/// Flags set by arithmetic, bit operations and CMP.
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Flags {
+    /// Z: the last result was 0.
+    pub zero: bool,
+    /// S: the last result was negative.
+    pub signed: bool,
+}
+
+/// Reason why the emulator stopped executing. All faults except `InvalidInstruction`
+/// are only detected if the corresponding check is enabled, see `Checks`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Fault {
+    /// The word at `address` is not an instruction the assembler could have produced.
+    InvalidInstruction { address: i32, word: u32 },
+    /// DIV or MOD by 0.
+    DivisionByZero { address: i32 },
+    /// PUSH, CALL or INT with SP below the stack region.
+    StackOverflow { address: i32, sp: i32 },
+    /// POP or RET with SP at or above the end of the stack region.
+    StackUnderflow { address: i32, sp: i32 },
+    /// RET without a preceding CALL or INT.
+    ReturnWithEmptyStack { address: i32 },
+    /// Memory read of `target`, which was neither loaded nor written before.
+    UninitializedRead { address: i32, target: i32 },
+    /// IP points to a word which was emitted by `.word` or `.space`.
+    ExecutingData { address: i32 },
+    /// The instruction at `address` continues execution at `target`, outside of the program.
+    JumpOutsideImage { address: i32, target: i32 },
+    /// The program did not halt within `steps` instructions.
+    StepLimit { address: i32, steps: u64 },
+}
+
+impl Fault {
+    /// Address of the instruction which caused the fault.
+    pub fn address(&self) -> i32 {
+        match *self {
+            Fault::InvalidInstruction { address, .. }
+            | Fault::DivisionByZero { address }
+            | Fault::StackOverflow { address, .. }
+            | Fault::StackUnderflow { address, .. }
+            | Fault::ReturnWithEmptyStack { address }
+            | Fault::UninitializedRead { address, .. }
+            | Fault::ExecutingData { address }
+            | Fault::JumpOutsideImage { address, .. }
+            | Fault::StepLimit { address, .. } => address,
+        }
+    }
+}
+
+impl fmt::Display for Fault {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Fault::InvalidInstruction { address, word } => write!(f, "Invalid instruction 0x{:08x} at address {}", word, address),
+            Fault::DivisionByZero { address } => write!(f, "Division by zero at address {}", address),
+            Fault::StackOverflow { address, sp } => write!(f, "Stack overflow (SP = {}) at address {}", sp, address),
+            Fault::StackUnderflow { address, sp } => write!(f, "Stack underflow (SP = {}) at address {}", sp, address),
+            Fault::ReturnWithEmptyStack { address } => write!(f, "Return with empty stack at address {}", address),
+            Fault::UninitializedRead { address, target } => write!(f, "Read of uninitialized memory [{}] at address {}", target, address),
+            Fault::ExecutingData { address } => write!(f, "Executing data at address {}", address),
+            Fault::JumpOutsideImage { address, target } => write!(f, "Jump to {} outside of the program at address {}", target, address),
+            Fault::StepLimit { address, steps } => write!(f, "No HALT within {} instructions, stopped at address {}", steps, address),
+        }
+    }
+}
+
+/// Runtime checks which turn silent misbehavior into faults. All checks are disabled by default.
+#[derive(Default, Debug, Clone, PartialEq, Eq)]
+pub struct Checks {
+    /// DIV and MOD by 0, which result in 0 in-game.
+    pub division_by_zero: bool,
+    /// Addresses the stack may use. SP starts at the end of the region and decreases.
+    pub stack: Option<RangeInclusive<i32>>,
+    /// RET without a matching CALL or INT.
+    pub returns: bool,
+    /// Memory reads of addresses which were neither loaded nor written. Devices are not checked.
+    pub uninitialized_reads: bool,
+    /// Addresses of the program. Continuing execution outside of them is a fault.
+    pub image: Option<Range<i32>>,
+    /// Addresses of data words, which must not be executed.
+    pub data: Vec<Range<i32>>,
+    /// `run` faults instead of returning `Ok(false)` if the program does not halt.
+    pub step_limit: bool,
+}
+
+impl Checks {
+    /// All checks for `program`. The stack is only checked if its region is given.
+    pub fn all(program: &Program, stack: Option<RangeInclusive<i32>>) -> Checks {
+        let start = program.start as i32;
+        Checks {
+            division_by_zero: true,
+            stack,
+            returns: true,
+            uninitialized_reads: true,
+            image: Some(start..start + (program.binary.len() / 4) as i32),
+            data: program.debug_info.lines.iter().filter(|line| line.data).map(|line| line.start as i32..line.end as i32).collect(),
+            step_limit: true,
+        }
+    }
+}
+
+/// Effects of a single executed instruction.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Step {
+    /// Address of the instruction.
+    pub address: i32,
+    pub instruction: Instruction,
+    /// Memory and device writes as `(address, value)`.
+    pub writes: Vec<(i32, i32)>,
+    /// Game ticks the instruction took according to the timing model.
+    pub ticks: u64,
+}
+
+/// Device attached to the addresses `start..start + device.size()`.
+struct MappedDevice {
+    start: i32,
+    device: Box<dyn Device>,
+}
+
+impl MappedDevice {
+    fn offset(&self, address: i32) -> Option<u32> {
+        let offset = address.wrapping_sub(self.start) as u32;
+        (address >= self.start && offset < self.device.size()).then_some(offset)
+    }
+}
+
+/// Executes programs instruction by instruction, as described in Architecture.md.
+///
+/// Memory is not limited in size: every address which was not written yet reads as 0.
+/// Reads and writes of instructions to addresses with an attached device go to the device instead.
+pub struct Emulator {
+    registers: [i32; IRRegister::ALL.len()],
+    pub flags: Flags,
+    memory: HashMap<i32, i32>,
+    devices: Vec<MappedDevice>,
+    /// Writes of the current step.
+    writes: Vec<(i32, i32)>,
+    /// Memory content before the writes of the current step, `None` if the address was not written yet.
+    /// Writes to devices can not be undone.
+    overwritten: Vec<(i32, Option<i32>)>,
+    decoder: Decoder,
+    timing: TimingModel,
+    checks: Checks,
+    /// Number of CALL and INT without RET.
+    call_depth: usize,
+    halted: bool,
+    /// Number of executed instructions.
+    pub steps: u64,
+    /// Game ticks of all executed instructions.
+    pub ticks: u64,
+}
+
+impl Emulator {
+    pub fn new() -> Emulator {
+        let mut emulator = Emulator {
+            registers: [0; IRRegister::ALL.len()],
+            flags: Flags::default(),
+            memory: HashMap::new(),
+            devices: Vec::new(),
+            writes: Vec::new(),
+            overwritten: Vec::new(),
+            decoder: Decoder::new(),
+            timing: TimingModel::default(),
+            checks: Checks::default(),
+            call_depth: 0,
+            halted: false,
+            steps: 0,
+            ticks: 0,
+        };
+        emulator.set_register(IRRegister::IP, PROGRAM_START as i32);
+        emulator
+    }
+
+    /// Creates an emulator with the rom and data of the program loaded.
+    /// Execution starts at the first word of the program.
+    pub fn with_program(program: &Program) -> Emulator {
+        let mut emulator = Emulator::new();
+        emulator.load(program.start, &program.binary);
+        emulator.load(program.data_start, &program.data);
+        emulator.set_register(IRRegister::IP, program.start as i32);
+        emulator
+    }
+
+    /// Copies the big-endian words of `binary` into memory, starting at `address`.
+    pub fn load(&mut self, address: u32, binary: &[u8]) {
+        for (offset, word) in binary.chunks_exact(4).enumerate() {
+            self.write(address as i32 + offset as i32, i32::from_be_bytes(word.try_into().unwrap()));
+        }
+    }
+
+    /// Replaces the default timing model used to count ticks.
+    pub fn set_timing(&mut self, timing: TimingModel) {
+        self.timing = timing;
+    }
+
+    /// Enables the given runtime checks.
+    pub fn set_checks(&mut self, checks: Checks) {
+        self.checks = checks;
+    }
+
+    /// Attaches a device at `start`. Panics if it overlaps with another device.
+    pub fn attach(&mut self, start: i32, device: Box<dyn Device>) {
+        let mapped = MappedDevice { start, device };
+        let last = start.wrapping_add(mapped.device.size() as i32 - 1);
+        if let Some(other) = self.devices.iter().find(|other| other.offset(start).is_some() || other.offset(last).is_some() || mapped.offset(other.start).is_some()) {
+            panic!("Device at {} overlaps with the device at {}", start, other.start);
+        }
+        self.devices.push(mapped);
+    }
+
+    /// Attached devices with their start address.
+    pub fn devices(&self) -> impl Iterator<Item = (i32, &dyn Device)> {
+        self.devices.iter().map(|mapped| (mapped.start, mapped.device.as_ref()))
+    }
+
+    pub fn register(&self, register: IRRegister) -> i32 {
+        self.registers[register as usize - 1]
+    }
+
+    pub fn set_register(&mut self, register: IRRegister, value: i32) {
+        self.registers[register as usize - 1] = value;
+    }
+
+    /// Memory content at `address`. Devices are not accessed.
+    pub fn read(&self, address: i32) -> i32 {
+        self.memory.get(&address).copied().unwrap_or(0)
+    }
+
+    /// Writes to memory. Devices are not accessed.
+    pub fn write(&mut self, address: i32, value: i32) {
+        self.memory.insert(address, value);
+    }
+
+    pub fn is_halted(&self) -> bool {
+        self.halted
+    }
+
+    /// Decodes the instruction at IP without executing it.
+    pub fn current_instruction(&self) -> Result<Instruction, Fault> {
+        let address = self.register(IRRegister::IP);
+        // The longest instruction has 3 words.
+        let words = (0..3).map(|offset| self.read(address.wrapping_add(offset)) as u32).collect::<Vec<_>>();
+        self.decoder.decode(&words).ok_or(Fault::InvalidInstruction { address, word: words[0] })
+    }
+
+    /// Executes a single instruction. Returns `None` if the emulator is halted.
+    pub fn step(&mut self) -> Result<Option<Step>, Fault> {
+        if self.halted {
+            return Ok(None);
+        }
+
+        let ip = self.register(IRRegister::IP);
+        if self.checks.data.iter().any(|data| data.contains(&ip)) {
+            return Err(Fault::ExecutingData { address: ip });
+        }
+        let instruction = self.current_instruction()?;
+        let operands = &instruction.operands;
+        // A jump outside of the image is only known after the instruction ran, its changes are undone then.
+        let (registers, flags, call_depth) = (self.registers, self.flags, self.call_depth);
+        self.writes.clear();
+        self.overwritten.clear();
+
+        // Jumps set IP to their target, all other instructions advance IP past
+        // themselves afterwards (also if they have written IP, e.g. RET).
+        let mut jump = None;
+        let location = match operands.first() {
+            Some(Operand::Location(location)) => ip.wrapping_add(*location),
+            _ => ip,
+        };
+        let mut jump_if = |condition: bool| {
+            if condition {
+                jump = Some(location);
+            }
+        };
+        match instruction.command {
+            IRCommand::Mov => {
+                let value = self.value(&operands[1])?;
+                self.store(&operands[0], value);
+            }
+            IRCommand::Add => self.compute(operands, Operation::Add)?,
+            IRCommand::Sub => self.compute(operands, Operation::Sub)?,
+            IRCommand::Mul => self.compute(operands, Operation::Mul)?,
+            IRCommand::Div => self.compute(operands, Operation::Div)?,
+            IRCommand::Mod => self.compute(operands, Operation::Mod)?,
+            IRCommand::Pow => self.compute(operands, Operation::Pow)?,
+            IRCommand::Cmp => {
+                let result = self.value(&operands[0])?.wrapping_sub(self.value(&operands[1])?);
+                self.set_flags(result);
+            }
+            IRCommand::Inc => self.compute(&[operands[0], Operand::Immediate(1)], Operation::Add)?,
+            IRCommand::Dec => self.compute(&[operands[0], Operand::Immediate(1)], Operation::Sub)?,
+            IRCommand::And => self.compute(operands, Operation::And)?,
+            IRCommand::Or => self.compute(operands, Operation::Or)?,
+            IRCommand::Xor => self.compute(operands, Operation::Xor)?,
+            IRCommand::Shl => self.compute(operands, Operation::Shl)?,
+            IRCommand::Shr => self.compute(operands, Operation::Shr)?,
+            IRCommand::Not => self.compute(&[operands[0], Operand::Immediate(-1)], Operation::Xor)?,
+            IRCommand::Jmp => jump_if(true),
+            IRCommand::Jz => jump_if(self.flags.zero),
+            IRCommand::Jnz => jump_if(!self.flags.zero),
+            IRCommand::Js => jump_if(self.flags.signed),
+            IRCommand::Jns => jump_if(!self.flags.signed),
+            IRCommand::Jle => jump_if(self.flags.zero || self.flags.signed),
+            IRCommand::Jgt => jump_if(!self.flags.zero && !self.flags.signed),
+            IRCommand::Push => {
+                let value = self.value(&operands[0])?;
+                self.push(value)?;
+            }
+            IRCommand::Pop => {
+                let value = self.pop()?;
+                self.store(&operands[0], value);
+            }
+            IRCommand::Call => {
+                self.push(ip)?;
+                self.call_depth += 1;
+                jump_if(true);
+            }
+            IRCommand::Int => {
+                let target = self.value(&operands[0])?;
+                self.push(ip)?;
+                self.call_depth += 1;
+                jump = Some(target);
+            }
+            IRCommand::Ret => {
+                if self.call_depth == 0 && self.checks.returns {
+                    return Err(Fault::ReturnWithEmptyStack { address: ip });
+                }
+                let value = self.pop()?;
+                self.call_depth = self.call_depth.saturating_sub(1);
+                self.set_register(IRRegister::IP, value);
+            }
+            IRCommand::Halt => {
+                self.halted = true;
+                jump = Some(ip);
+            }
+            IRCommand::Nop => {}
+        }
+
+        let next = jump.unwrap_or_else(|| self.register(IRRegister::IP).wrapping_add(instruction.size as i32));
+        if let Some(image) = &self.checks.image {
+            if !image.contains(&next) {
+                for (address, value) in self.overwritten.drain(..).rev() {
+                    match value {
+                        Some(value) => self.memory.insert(address, value),
+                        None => self.memory.remove(&address),
+                    };
+                }
+                (self.registers, self.flags, self.call_depth, self.halted) = (registers, flags, call_depth, false);
+                return Err(Fault::JumpOutsideImage { address: ip, target: next });
+            }
+        }
+
+        self.steps += 1;
+        let ticks = self.timing.ticks(&instruction);
+        self.ticks += ticks;
+        for mapped in &mut self.devices {
+            mapped.device.tick(ticks);
+        }
+        self.set_register(IRRegister::IP, next);
+        Ok(Some(Step {
+            address: ip,
+            instruction,
+            writes: std::mem::take(&mut self.writes),
+            ticks,
+        }))
+    }
+
+    /// Executes instructions until HALT. Returns `Ok(false)` if the program
+    /// did not halt within `max_steps` instructions, or `Fault::StepLimit`
+    /// if that check is enabled.
+    pub fn run(&mut self, max_steps: u64) -> Result<bool, Fault> {
+        self.run_with(max_steps, |_, _| {})
+    }
+
+    /// Like `run`, but calls `observer` after every executed instruction.
+    pub fn run_with(&mut self, max_steps: u64, mut observer: impl FnMut(&Emulator, &Step)) -> Result<bool, Fault> {
+        for _ in 0..max_steps {
+            match self.step()? {
+                Some(step) => observer(self, &step),
+                None => break,
+            }
+        }
+        if !self.halted && self.checks.step_limit {
+            return Err(Fault::StepLimit {
+                address: self.register(IRRegister::IP),
+                steps: max_steps,
+            });
+        }
+        Ok(self.halted)
+    }
+
+    /// Memory access of an instruction, which may go to a device.
+    fn load_word(&mut self, address: i32) -> Result<i32, Fault> {
+        match self.devices.iter_mut().find_map(|mapped| Some((mapped.offset(address)?, mapped))) {
+            Some((offset, mapped)) => Ok(mapped.device.read(offset)),
+            None if self.checks.uninitialized_reads && !self.memory.contains_key(&address) => Err(Fault::UninitializedRead {
+                address: self.register(IRRegister::IP),
+                target: address,
+            }),
+            None => Ok(self.read(address)),
+        }
+    }
+
+    fn store_word(&mut self, address: i32, value: i32) {
+        self.writes.push((address, value));
+        match self.devices.iter_mut().find_map(|mapped| Some((mapped.offset(address)?, mapped))) {
+            Some((offset, mapped)) => mapped.device.write(offset, value),
+            None => {
+                self.overwritten.push((address, self.memory.get(&address).copied()));
+                self.write(address, value);
+            }
+        }
+    }
+
+    fn value(&mut self, operand: &Operand) -> Result<i32, Fault> {
+        match *operand {
+            Operand::Register(register) => Ok(self.register(register)),
+            Operand::Immediate(value) | Operand::Location(value) => Ok(value),
+            Operand::MemoryAtRegister(register) => self.load_word(self.register(register)),
+            Operand::MemoryAtImmediate(address) => self.load_word(address),
+        }
+    }
+
+    fn store(&mut self, operand: &Operand, value: i32) {
+        match *operand {
+            Operand::Register(register) => self.set_register(register, value),
+            Operand::MemoryAtRegister(register) => self.store_word(self.register(register), value),
+            Operand::MemoryAtImmediate(address) => self.store_word(address, value),
+            Operand::Immediate(_) | Operand::Location(_) => unreachable!("Decoder produced an immediate as target"),
+        }
+    }
+
+    /// Applies `operation` to both operands, stores the result in the first one and sets the flags.
+    fn compute(&mut self, operands: &[Operand], operation: Operation) -> Result<(), Fault> {
+        let (a, b) = (self.value(&operands[0])?, self.value(&operands[1])?);
+        if b == 0 && matches!(operation, Operation::Div | Operation::Mod) && self.checks.division_by_zero {
+            return Err(Fault::DivisionByZero { address: self.register(IRRegister::IP) });
+        }
+        let result = operation.apply(a, b);
+        self.store(&operands[0], result);
+        self.set_flags(result);
+        Ok(())
+    }
+
+    fn set_flags(&mut self, result: i32) {
+        self.flags = Flags {
+            zero: result == 0,
+            signed: result < 0,
+        };
+    }
+
+    /// The stack grows towards negative addresses: `[SP] = value; SP--`.
+    fn push(&mut self, value: i32) -> Result<(), Fault> {
+        let sp = self.register(IRRegister::SP);
+        if self.checks.stack.as_ref().is_some_and(|stack| !stack.contains(&sp)) {
+            return Err(Fault::StackOverflow { address: self.register(IRRegister::IP), sp });
+        }
+        self.store_word(sp, value);
+        self.set_register(IRRegister::SP, sp.wrapping_sub(1));
+        Ok(())
+    }
+
+    /// `SP++; value = [SP]`
+    fn pop(&mut self) -> Result<i32, Fault> {
+        let sp = self.register(IRRegister::SP);
+        if self.checks.stack.as_ref().is_some_and(|stack| !stack.contains(&sp.wrapping_add(1))) {
+            return Err(Fault::StackUnderflow { address: self.register(IRRegister::IP), sp });
+        }
+        self.set_register(IRRegister::SP, sp.wrapping_add(1));
+        self.load_word(sp.wrapping_add(1))
+    }
+}
+
+impl Default for Emulator {
+    fn default() -> Emulator {
+        Emulator::new()
+    }
+}
+
+impl fmt::Display for Emulator {
+    /// Register and flag state, e.g. for printing after a run.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let registers = IRRegister::ALL.iter().map(|&register| format!("{} = {}", register.name(), self.register(register))).collect::<Vec<_>>();
+        writeln!(f, "{}", registers.join(", "))?;
+        write!(f, "Z = {}, S = {}", self.flags.zero as u8, self.flags.signed as u8)
+    }
+}
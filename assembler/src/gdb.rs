@@ -0,0 +1,419 @@
+use std::{
+    collections::HashSet,
+    io::{self, BufRead, BufReader, ErrorKind, Read, Write},
+    net::{TcpListener, TcpStream},
+};
+
+use crate::{
+    emulator::{Emulator, Fault, Flags},
+    ir::IRRegister,
+};
+
+/// Registers in the order of the target description. The flags follow as last register.
+const REGISTERS: [IRRegister; 6] = IRRegister::ALL;
+const FLAGS_REGISTER: usize = REGISTERS.len();
+
+/// Bytes per word. The client addresses bytes, the emulator words.
+const WORD_SIZE: i32 = 4;
+
+/// Instructions executed between checks for an interrupt (Ctrl-C) from the client.
+const INTERRUPT_CHECK_INTERVAL: u64 = 10_000;
+
+/// GDB has no architecture for the CPU, so the description names none and GDB falls back to
+/// its default. Words are sent big-endian, which GDB only shows correctly after `set endian big`.
+const TARGET_XML: &str = r#"<?xml version="1.0"?>
+<!DOCTYPE target SYSTEM "gdb-target.dtd">
+<target version="1.0">
+  <!-- 32 bit big-endian registers and memory words. Addresses are byte addresses, ip and sp are 4 times the word address. -->
+  <feature name="org.factorio-cpu.core">
+    <flags id="flags_type" size="4">
+      <field name="Z" start="0" end="0"/>
+      <field name="S" start="1" end="1"/>
+    </flags>
+    <reg name="a" bitsize="32" type="int32" regnum="0"/>
+    <reg name="b" bitsize="32" type="int32"/>
+    <reg name="c" bitsize="32" type="int32"/>
+    <reg name="d" bitsize="32" type="int32"/>
+    <reg name="ip" bitsize="32" type="code_ptr"/>
+    <reg name="sp" bitsize="32" type="data_ptr"/>
+    <reg name="flags" bitsize="32" type="flags_type"/>
+  </feature>
+</target>
+"#;
+
+/// Reason the target stopped, reported to the client.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Stop {
+    Step,
+    Breakpoint,
+    Interrupt,
+    Halt,
+    Fault(Fault),
+}
+
+impl Stop {
+    fn reply(&self) -> String {
+        match self {
+            Stop::Step => "S05".into(),
+            Stop::Breakpoint => "T05swbreak:;".into(),
+            Stop::Interrupt => "S02".into(),
+            // The program exited with status 0.
+            Stop::Halt => "W00".into(),
+            Stop::Fault(fault) => match fault {
+                // SIGFPE
+                Fault::DivisionByZero { .. } => "S08".into(),
+                // SIGILL
+                Fault::InvalidInstruction { .. } | Fault::ExecutingData { .. } => "S04".into(),
+                // SIGTRAP
+                Fault::StepLimit { .. } => "S05".into(),
+                // SIGSEGV
+                Fault::StackOverflow { .. } | Fault::StackUnderflow { .. } | Fault::ReturnWithEmptyStack { .. } | Fault::UninitializedRead { .. } | Fault::JumpOutsideImage { .. } => "S0b".into(),
+            },
+        }
+    }
+}
+
+/// Server for the GDB remote serial protocol, which controls an emulator.
+///
+/// The client sees byte addresses like on any other target: a word address is sent as 4 times its
+/// value, in IP and SP as well as in memory and breakpoint packets, so `m 40,8` reads the words at
+/// the addresses 0x10 and 0x11. Addresses have to be word aligned. Registers and words are sent in
+/// big-endian byte order.
+pub struct GdbServer {
+    emulator: Emulator,
+    breakpoints: HashSet<i32>,
+    no_ack: bool,
+    /// Reason of the last stop, the target is stopped after a step when the session starts.
+    stop: Stop,
+}
+
+impl GdbServer {
+    pub fn new(emulator: Emulator) -> GdbServer {
+        GdbServer {
+            emulator,
+            breakpoints: HashSet::new(),
+            no_ack: false,
+            stop: Stop::Step,
+        }
+    }
+
+    pub fn emulator(&self) -> &Emulator {
+        &self.emulator
+    }
+
+    /// Accepts a single client and serves it until it detaches or disconnects.
+    pub fn serve(&mut self, listener: &TcpListener) -> io::Result<()> {
+        let (stream, _) = listener.accept()?;
+        stream.set_nodelay(true)?;
+        let mut connection = Connection {
+            reader: BufReader::new(stream.try_clone()?),
+            writer: stream,
+        };
+
+        while let Some(packet) = connection.read_packet(self.no_ack)? {
+            let reply = match packet.as_str() {
+                // Ctrl-C while stopped.
+                "\x03" => {
+                    self.stop = Stop::Interrupt;
+                    Some(self.stop.reply())
+                }
+                _ => self.handle(&packet, &mut || connection.interrupted()),
+            };
+            match reply {
+                Some(reply) => connection.write_packet(&reply)?,
+                None => {
+                    connection.write_packet("OK")?;
+                    break;
+                }
+            }
+            if packet == "QStartNoAckMode" {
+                self.no_ack = true;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Handles a single packet. Returns the reply, or `None` if the session ends.
+    fn handle(&mut self, packet: &str, interrupted: &mut dyn FnMut() -> bool) -> Option<String> {
+        let (command, arguments) = packet.split_at(packet.chars().next().map_or(0, char::len_utf8));
+        let reply = match command {
+            "?" => self.stop.reply(),
+            "g" => (0..=FLAGS_REGISTER).map(|number| hex_word(self.register(number))).collect(),
+            "G" => self.write_registers(arguments),
+            "p" => match usize::from_str_radix(arguments, 16) {
+                Ok(number) if number <= FLAGS_REGISTER => hex_word(self.register(number)),
+                _ => "E01".into(),
+            },
+            "P" => self.write_register(arguments),
+            "m" => self.read_memory(arguments),
+            "M" => self.write_memory(arguments),
+            "Z" | "z" => self.breakpoint(command == "Z", arguments),
+            "s" | "c" => {
+                self.stop = self.resume(command == "s", interrupted);
+                self.stop.reply()
+            }
+            "H" => "OK".into(),
+            "k" | "D" => return None,
+            "q" | "Q" => self.query(packet),
+            // Unsupported packets get an empty reply.
+            _ => String::new(),
+        };
+        Some(reply)
+    }
+
+    fn query(&self, packet: &str) -> String {
+        if packet.starts_with("qSupported") {
+            return "PacketSize=4000;qXfer:features:read+;swbreak+;QStartNoAckMode+".into();
+        }
+        if let Some(range) = packet.strip_prefix("qXfer:features:read:target.xml:") {
+            return match parse_range(range) {
+                Some((offset, length)) => {
+                    let offset = (offset as usize).min(TARGET_XML.len());
+                    let end = (offset + length as usize).min(TARGET_XML.len());
+                    let marker = if end == TARGET_XML.len() { 'l' } else { 'm' };
+                    format!("{}{}", marker, &TARGET_XML[offset..end])
+                }
+                None => "E01".into(),
+            };
+        }
+
+        match packet {
+            "QStartNoAckMode" => "OK".into(),
+            "qAttached" => "1".into(),
+            "qC" => "QC1".into(),
+            "qfThreadInfo" => "m1".into(),
+            "qsThreadInfo" => "l".into(),
+            _ => String::new(),
+        }
+    }
+
+    fn register(&self, number: usize) -> i32 {
+        match number {
+            FLAGS_REGISTER => self.emulator.flags.zero as i32 | (self.emulator.flags.signed as i32) << 1,
+            number if is_pointer(number) => self.emulator.register(REGISTERS[number]).wrapping_mul(WORD_SIZE),
+            number => self.emulator.register(REGISTERS[number]),
+        }
+    }
+
+    /// Returns `false` without changing the register if a pointer is not word aligned.
+    fn set_register(&mut self, number: usize, value: i32) -> bool {
+        match number {
+            FLAGS_REGISTER => {
+                self.emulator.flags = Flags {
+                    zero: value & 1 != 0,
+                    signed: value & 2 != 0,
+                }
+            }
+            number if is_pointer(number) => match word_address(value) {
+                Some(address) => self.emulator.set_register(REGISTERS[number], address),
+                None => return false,
+            },
+            number => self.emulator.set_register(REGISTERS[number], value),
+        }
+        true
+    }
+
+    fn write_registers(&mut self, arguments: &str) -> String {
+        match parse_words(arguments) {
+            Some(values) if values.len() == FLAGS_REGISTER + 1 && (0..=FLAGS_REGISTER).all(|number| !is_pointer(number) || word_address(values[number]).is_some()) => {
+                for (number, value) in values.into_iter().enumerate() {
+                    self.set_register(number, value);
+                }
+                "OK".into()
+            }
+            _ => "E01".into(),
+        }
+    }
+
+    fn write_register(&mut self, arguments: &str) -> String {
+        let parsed = arguments.split_once('=').and_then(|(number, value)| {
+            let number = usize::from_str_radix(number, 16).ok().filter(|&number| number <= FLAGS_REGISTER)?;
+            Some((number, *parse_words(value)?.first()?))
+        });
+        match parsed {
+            Some((number, value)) if self.set_register(number, value) => "OK".into(),
+            _ => "E01".into(),
+        }
+    }
+
+    /// `m ADDRESS,LENGTH` where the address and the length are in bytes and have to be multiples
+    /// of 4, so the reply holds the words at `ADDRESS / 4` to `(ADDRESS + LENGTH) / 4 - 1`.
+    fn read_memory(&self, arguments: &str) -> String {
+        match parse_word_range(arguments) {
+            Some((address, length)) => (0..length / 4).map(|offset| hex_word(self.emulator.read(address.wrapping_add(offset as i32)))).collect(),
+            None => "E01".into(),
+        }
+    }
+
+    /// `M ADDRESS,LENGTH:DATA` with the address and the length in bytes, like `m`.
+    fn write_memory(&mut self, arguments: &str) -> String {
+        let parsed = arguments.split_once(':').and_then(|(range, data)| Some((parse_word_range(range)?, parse_words(data)?)));
+        match parsed {
+            Some(((address, length), words)) if words.len() * 4 == length as usize => {
+                for (offset, word) in words.into_iter().enumerate() {
+                    self.emulator.write(address.wrapping_add(offset as i32), word);
+                }
+                "OK".into()
+            }
+            _ => "E01".into(),
+        }
+    }
+
+    /// `Z0,ADDRESS,KIND` and `z0,ADDRESS,KIND` with a byte address. Only software breakpoints are
+    /// supported.
+    fn breakpoint(&mut self, insert: bool, arguments: &str) -> String {
+        let mut parts = arguments.split(',');
+        let address = match (parts.next(), parts.next().and_then(parse_hex)) {
+            (Some("0"), Some(address)) => match word_address(address) {
+                Some(address) => address,
+                None => return "E01".into(),
+            },
+            _ => return String::new(),
+        };
+        if insert {
+            self.breakpoints.insert(address);
+        } else {
+            self.breakpoints.remove(&address);
+        }
+        "OK".into()
+    }
+
+    fn resume(&mut self, single_step: bool, interrupted: &mut dyn FnMut() -> bool) -> Stop {
+        loop {
+            match self.emulator.step() {
+                Ok(Some(_)) if self.emulator.is_halted() => return Stop::Halt,
+                Ok(Some(_)) => {}
+                Ok(None) => return Stop::Halt,
+                Err(fault) => {
+                    eprintln!("{}", fault);
+                    return Stop::Fault(fault);
+                }
+            }
+
+            if single_step {
+                return Stop::Step;
+            }
+            if self.breakpoints.contains(&self.emulator.register(IRRegister::IP)) {
+                return Stop::Breakpoint;
+            }
+            if self.emulator.steps.is_multiple_of(INTERRUPT_CHECK_INTERVAL) && interrupted() {
+                return Stop::Interrupt;
+            }
+        }
+    }
+}
+
+struct Connection {
+    reader: BufReader<TcpStream>,
+    writer: TcpStream,
+}
+
+impl Connection {
+    /// Reads the next packet and acknowledges it. A Ctrl-C is returned as `"\x03"`.
+    /// Returns `None` when the client disconnected.
+    fn read_packet(&mut self, no_ack: bool) -> io::Result<Option<String>> {
+        loop {
+            let mut byte = [0];
+            if self.reader.read(&mut byte)? == 0 {
+                return Ok(None);
+            }
+            match byte[0] {
+                b'$' => {}
+                0x03 => return Ok(Some("\x03".into())),
+                // Acknowledgements of our replies.
+                _ => continue,
+            }
+
+            let mut data = Vec::new();
+            if self.reader.read_until(b'#', &mut data)? == 0 {
+                return Ok(None);
+            }
+            data.pop();
+            let mut checksum = [0; 2];
+            self.reader.read_exact(&mut checksum)?;
+
+            let valid = std::str::from_utf8(&checksum).ok().and_then(|checksum| u8::from_str_radix(checksum, 16).ok()) == Some(checksum_of(&data));
+            if !no_ack {
+                self.writer.write_all(if valid { b"+" } else { b"-" })?;
+            }
+            if valid {
+                return Ok(Some(String::from_utf8_lossy(&data).into_owned()));
+            }
+        }
+    }
+
+    fn write_packet(&mut self, data: &str) -> io::Result<()> {
+        write!(self.writer, "${}#{:02x}", data, checksum_of(data.as_bytes()))?;
+        self.writer.flush()
+    }
+
+    /// Checks without blocking whether the client sent a Ctrl-C.
+    fn interrupted(&mut self) -> bool {
+        if self.reader.buffer().contains(&0x03) {
+            self.reader.consume(self.reader.buffer().len());
+            return true;
+        }
+
+        let mut byte = [0];
+        let stream = self.reader.get_ref();
+        if stream.set_nonblocking(true).is_err() {
+            return false;
+        }
+        let result = stream.peek(&mut byte);
+        let _ = stream.set_nonblocking(false);
+        match result {
+            Ok(1) if byte[0] == 0x03 => {
+                let _ = self.reader.read(&mut byte);
+                true
+            }
+            // The client disconnected.
+            Ok(0) => true,
+            Err(e) if e.kind() != ErrorKind::WouldBlock => true,
+            _ => false,
+        }
+    }
+}
+
+/// IP and SP hold word addresses.
+fn is_pointer(number: usize) -> bool {
+    number < REGISTERS.len() && matches!(REGISTERS[number], IRRegister::IP | IRRegister::SP)
+}
+
+/// Word address of a word aligned byte address. Negative word addresses are sent as negative byte
+/// addresses, so only word addresses from -2^29 to 2^29 - 1 are reachable.
+fn word_address(address: i32) -> Option<i32> {
+    (address % WORD_SIZE == 0).then_some(address / WORD_SIZE)
+}
+
+fn checksum_of(data: &[u8]) -> u8 {
+    data.iter().fold(0, |sum, &byte| sum.wrapping_add(byte))
+}
+
+fn hex_word(value: i32) -> String {
+    format!("{:08x}", value as u32)
+}
+
+fn parse_hex(value: &str) -> Option<i32> {
+    u32::from_str_radix(value, 16).ok().map(|value| value as i32)
+}
+
+/// `ADDRESS,LENGTH` in hexadecimal.
+fn parse_range(range: &str) -> Option<(i32, u32)> {
+    let (address, length) = range.split_once(',')?;
+    Some((parse_hex(address)?, u32::from_str_radix(length, 16).ok()?))
+}
+
+/// `ADDRESS,LENGTH` in bytes, converted to the word address. Both have to be word aligned.
+fn parse_word_range(range: &str) -> Option<(i32, u32)> {
+    let (address, length) = parse_range(range).filter(|(_, length)| length.is_multiple_of(WORD_SIZE as u32))?;
+    Some((word_address(address)?, length))
+}
+
+/// Big-endian 32 bit words in hexadecimal.
+fn parse_words(data: &str) -> Option<Vec<i32>> {
+    if !data.len().is_multiple_of(8) || !data.is_ascii() {
+        return None;
+    }
+    (0..data.len()).step_by(8).map(|index| parse_hex(&data[index..index + 8])).collect()
+}
@@ -0,0 +1,191 @@
+use crate::{
+    arithmetic::Operation,
+    blueprint::{ArithmeticConditions, Blueprint, ControlBehavior, DeciderConditions, Entity, Filter, Position, SignalId, Wire},
+    circuit::{Point, WireColor},
+    microarchitecture::DecodeModel,
+};
+
+const INPUT: Point = 1;
+const OUTPUT: Point = 2;
+
+/// Decider combinators which raise Yellow on their output if `I` (the instruction type) matches
+/// their opcode, one for every instruction of the assembler, ordered by opcode. Their inputs are
+/// connected by a red wire, the outputs have to be wired to the units executing the instructions.
+pub fn decoder_blueprint() -> Blueprint {
+    let mut builder = Builder::default();
+    let mut previous = None;
+    for (index, opcode) in DecodeModel::new().opcodes().into_iter().enumerate() {
+        // Deciders are 1x2, facing north.
+        let decider = builder.add("decider-combinator", index as f64 + 0.5, 1.0, decider("signal-I", "=", opcode as i32, "signal-yellow", false));
+        if let Some(previous) = previous {
+            builder.connect(WireColor::Red, (previous, INPUT), (decider, INPUT));
+        }
+        previous = Some(decider);
+    }
+    builder.build("Instruction Decoder")
+}
+
+/// Memory bank of `size` words at the addresses `start..start + size`, one column of combinators per word:
+///
+/// * A latch decider holds the value on Green as long as `W` is not its address.
+/// * A write decider passes Green from the data input into the latch while `W` is its address.
+/// * A read decider passes the value of the latch to the data output while `R` is its address.
+///
+/// The interface is at the first column: the write address `W` on the red input wire of the
+/// latch, the data to write on the green input wire of the write decider, the read address `R`
+/// on the red input wire and the read data on the green output wire of the read decider. Writes
+/// need `W` and the data for one tick, reads take one tick. Address 0 can not be part of the bank,
+/// because `W = 0` means that nothing is written.
+///
+/// The words of `data` at `data_start` are the initial content. They are written by a one-tick pulse
+/// on Pink from the constant combinator left of the first column. Bots build the entities in any
+/// order, so the constant combinator is off in the blueprint: switch it on once the whole bank is
+/// built, the words are present three ticks later.
+pub fn ram_blueprint(start: u32, size: u32, data_start: u32, data: &[u8]) -> Result<Blueprint, String> {
+    let end = start.checked_add(size).filter(|&end| start > 0 && size > 0 && end <= i32::MAX as u32).ok_or_else(|| format!("Invalid memory bank of {} words at address {}", size, start))?;
+    let words = data.chunks_exact(4).map(|word| i32::from_be_bytes(word.try_into().unwrap())).collect::<Vec<_>>();
+    if !words.is_empty() && (data_start < start || data_start as u64 + words.len() as u64 > end as u64) {
+        return Err(format!("Data at addresses {}..{} is outside of the memory bank at {}..{}", data_start, data_start as u64 + words.len() as u64, start, end));
+    }
+    // Words before the data get an initializer as well, so the pulse can be chained through all columns.
+    let initialized = if words.is_empty() { 0 } else { data_start - start + words.len() as u32 };
+
+    let mut builder = Builder::default();
+    let pulse = if initialized > 0 {
+        // The constant Pink and its negation delayed by one tick cancel out after the first tick.
+        let constant = builder.add("constant-combinator", -0.5, 0.5, ControlBehavior { is_on: Some(false), ..constant("signal-pink", 1) });
+        let delay = builder.add("arithmetic-combinator", -0.5, 2.0, arithmetic("signal-pink", Operation::Mul, -1, "signal-pink"));
+        let edge = builder.add("decider-combinator", -0.5, 4.0, decider("signal-pink", ">", 0, "signal-pink", false));
+        // Constant combinators output on their only connection point.
+        builder.connect(WireColor::Red, (constant, INPUT), (delay, INPUT));
+        builder.connect(WireColor::Red, (delay, INPUT), (edge, INPUT));
+        builder.connect(WireColor::Green, (delay, OUTPUT), (edge, INPUT));
+        Some(edge)
+    } else {
+        None
+    };
+
+    let mut previous: Option<Cell> = None;
+    for offset in 0..size {
+        let address = (start + offset) as i32;
+        let x = offset as f64 + 0.5;
+        let cell = Cell {
+            latch: builder.add("decider-combinator", x, 1.0, decider("signal-W", "≠", address, "signal-green", true)),
+            write: builder.add("decider-combinator", x, 3.0, decider("signal-W", "=", address, "signal-green", true)),
+            read: builder.add("decider-combinator", x, 5.0, decider("signal-R", "=", address, "signal-green", true)),
+            init: (offset < initialized).then(|| {
+                let value = (start + offset).checked_sub(data_start).and_then(|index| words.get(index as usize)).copied().unwrap_or(0);
+                builder.add("arithmetic-combinator", x, 7.0, arithmetic("signal-pink", Operation::Mul, value, "signal-green"))
+            }),
+        };
+        builder.connect(WireColor::Red, (cell.latch, INPUT), (cell.write, INPUT));
+        builder.connect(WireColor::Green, (cell.latch, OUTPUT), (cell.latch, INPUT));
+        builder.connect(WireColor::Green, (cell.latch, OUTPUT), (cell.write, OUTPUT));
+        builder.connect(WireColor::Green, (cell.latch, INPUT), (cell.read, INPUT));
+        if let Some(init) = cell.init {
+            builder.connect(WireColor::Green, (init, OUTPUT), (cell.read, INPUT));
+        }
+
+        match (&previous, cell.init) {
+            (Some(previous), init) => {
+                builder.connect(WireColor::Red, (previous.latch, INPUT), (cell.latch, INPUT));
+                builder.connect(WireColor::Green, (previous.write, INPUT), (cell.write, INPUT));
+                builder.connect(WireColor::Red, (previous.read, INPUT), (cell.read, INPUT));
+                builder.connect(WireColor::Green, (previous.read, OUTPUT), (cell.read, OUTPUT));
+                if let (Some(previous_init), Some(init)) = (previous.init, init) {
+                    builder.connect(WireColor::Green, (previous_init, INPUT), (init, INPUT));
+                }
+            }
+            (None, Some(init)) => builder.connect(WireColor::Green, (pulse.expect("Pulse for initialized memory"), OUTPUT), (init, INPUT)),
+            (None, None) => {}
+        }
+        previous = Some(cell);
+    }
+    Ok(builder.build("Memory Bank"))
+}
+
+/// Combinators of one word of a memory bank.
+struct Cell {
+    latch: u32,
+    write: u32,
+    read: u32,
+    init: Option<u32>,
+}
+
+/// Entities of a generated blueprint, numbered in the order they are added.
+#[derive(Default)]
+struct Builder {
+    entities: Vec<Entity>,
+}
+
+impl Builder {
+    fn add(&mut self, name: &str, x: f64, y: f64, control_behavior: ControlBehavior) -> u32 {
+        let entity_number = self.entities.len() as u32 + 1;
+        self.entities.push(Entity {
+            entity_number,
+            name: name.into(),
+            position: Position { x, y },
+            direction: None,
+            control_behavior: Some(control_behavior),
+            connections: Default::default(),
+        });
+        entity_number
+    }
+
+    /// Connects two points with a wire. Like in Factorio, the wire is listed at both ends.
+    fn connect(&mut self, color: WireColor, a: (u32, Point), b: (u32, Point)) {
+        for ((entity, point), (other, other_point)) in [(a, b), (b, a)] {
+            let connection = self.entities[entity as usize - 1].connections.entry(point.to_string()).or_default();
+            let wires = match color {
+                WireColor::Red => &mut connection.red,
+                WireColor::Green => &mut connection.green,
+            };
+            wires.push(Wire {
+                entity_id: other,
+                circuit_id: Some(other_point),
+            });
+        }
+    }
+
+    fn build(self, label: &str) -> Blueprint {
+        Blueprint::new(label, self.entities)
+    }
+}
+
+fn constant(signal: &str, count: i32) -> ControlBehavior {
+    ControlBehavior {
+        filters: Some(vec![Filter {
+            signal: SignalId::virtual_signal(signal),
+            count,
+            index: 1,
+        }]),
+        ..ControlBehavior::default()
+    }
+}
+
+fn arithmetic(first: &str, operation: Operation, constant: i32, output: &str) -> ControlBehavior {
+    ControlBehavior {
+        arithmetic_conditions: Some(ArithmeticConditions {
+            first_signal: Some(SignalId::virtual_signal(first)),
+            second_constant: Some(constant),
+            operation: operation.symbol().into(),
+            output_signal: Some(SignalId::virtual_signal(output)),
+            ..ArithmeticConditions::default()
+        }),
+        ..ControlBehavior::default()
+    }
+}
+
+fn decider(first: &str, comparator: &str, constant: i32, output: &str, copy_count_from_input: bool) -> ControlBehavior {
+    ControlBehavior {
+        decider_conditions: Some(DeciderConditions {
+            first_signal: Some(SignalId::virtual_signal(first)),
+            second_signal: None,
+            constant: Some(constant),
+            comparator: comparator.into(),
+            output_signal: Some(SignalId::virtual_signal(output)),
+            copy_count_from_input,
+        }),
+        ..ControlBehavior::default()
+    }
+}
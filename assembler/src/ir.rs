@@ -1,9 +1,14 @@
 use std::collections::HashMap;
 
+use crate::object::SectionKind;
+
 const COMMENT_CHAR: char = ';';
+const DIRECTIVE_CHAR: char = '.';
 
 #[derive(Debug)]
 pub struct IR {
+    /// Name of the source file the instructions were read from.
+    pub source: String,
     pub instructions: Vec<IRLine>,
 }
 
@@ -11,6 +16,21 @@ pub struct IR {
 pub enum IRLine {
     Ins(IRInstruction),
     Label(String),
+    Directive(IRDirective, usize),
+}
+
+#[derive(Debug)]
+pub enum IRDirective {
+    /// Labels that are visible to other objects when linking.
+    Global(Vec<String>),
+    /// Labels that are defined in another object.
+    Extern(Vec<String>),
+    /// Place the following lines into the named section.
+    Section(String, SectionKind),
+    /// Words with the given values (immediates or label addresses).
+    Word(Vec<IRParameter>),
+    /// Reserve the given number of words, initialized with 0.
+    Space(u32),
 }
 
 #[derive(Debug)]
@@ -68,6 +88,7 @@ pub enum IRParameter {
     Label(String),
     MemReg(IRRegister),
     MemImm(i32),
+    MemLabel(String),
 }
 
 #[derive(Hash, Eq, PartialEq, Clone, Debug)]
@@ -80,7 +101,7 @@ pub enum IRParamType {
     MemoryAtImmediate,
 }
 
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
 #[repr(u8)]
 pub enum IRRegister {
     A = 0x01,
@@ -92,6 +113,7 @@ pub enum IRRegister {
 }
 
 impl IRInstruction {
+    #[allow(clippy::len_zero)]
     fn with_cmd_and_params_string(command: IRCommand, params: &str, line_number: usize) -> Option<IRInstruction> {
         if params.len() == 0 {
             return Some(IRInstruction { command, param1: None, param2: None, line_number });
@@ -118,6 +140,50 @@ impl IRInstruction {
 }
 
 impl IRCommand {
+    pub fn mnemonic(&self) -> &'static str {
+        match self {
+            Self::Mov => "MOV",
+            Self::Add => "ADD",
+            Self::Sub => "SUB",
+            Self::Mul => "MUL",
+            Self::Div => "DIV",
+            Self::Mod => "MOD",
+            Self::Pow => "POW",
+            Self::Cmp => "CMP",
+            Self::Inc => "INC",
+            Self::Dec => "DEC",
+            Self::And => "AND",
+            Self::Or => "OR",
+            Self::Xor => "XOR",
+            Self::Shl => "SHL",
+            Self::Shr => "SHR",
+            Self::Not => "NOT",
+            Self::Jmp => "JMP",
+            Self::Jz => "JZ",
+            Self::Jnz => "JNZ",
+            Self::Js => "JS",
+            Self::Jns => "JNS",
+            Self::Jle => "JLE",
+            Self::Jgt => "JGT",
+            Self::Push => "PUSH",
+            Self::Pop => "POP",
+            Self::Call => "CALL",
+            Self::Int => "INT",
+            Self::Ret => "RET",
+            Self::Halt => "HALT",
+            Self::Nop => "NOP",
+        }
+    }
+
+    /// Jumps and calls encode their label parameter as a location relative
+    /// to the instruction. All other commands use the absolute address of a label.
+    pub fn is_relative_jump(&self) -> bool {
+        matches!(
+            self,
+            Self::Jmp | Self::Jz | Self::Jnz | Self::Js | Self::Jns | Self::Jle | Self::Jgt | Self::Call
+        )
+    }
+
     fn translation_table() -> HashMap<&'static str, IRCommand> {
         HashMap::from([
             ("mov", Self::Mov),
@@ -166,9 +232,11 @@ impl IRParameter {
             IRParameter::Label(_) => IRParamType::Label,
             IRParameter::MemReg(_) => IRParamType::MemoryAtRegister,
             IRParameter::MemImm(_) => IRParamType::MemoryAtImmediate,
+            IRParameter::MemLabel(_) => IRParamType::MemoryAtImmediate,
         }
     }
 
+    #[allow(clippy::comparison_to_empty, clippy::is_digit_ascii_radix)]
     fn from(param: &str, line_number: usize) -> Option<IRParameter> {
         let param = param.trim();
         if param == "" {
@@ -185,6 +253,7 @@ impl IRParameter {
             return match inner {
                 Some(IRParameter::Reg(register)) => Some(IRParameter::MemReg(register)),
                 Some(IRParameter::Imm(value)) => Some(IRParameter::MemImm(value)),
+                Some(IRParameter::Label(label)) => Some(IRParameter::MemLabel(label)),
                 None => None, // Already printed an error message.
                 Some(_) => {
                     eprintln!("Invalid parameter '{}' on line {}", param, line_number);
@@ -201,7 +270,7 @@ impl IRParameter {
             return Some(IRParameter::Reg(register));
         }
 
-        if param.chars().all(char::is_alphanumeric) {
+        if is_label_name(param) {
             return Some(IRParameter::Label(param.into()));
         }
 
@@ -210,27 +279,99 @@ impl IRParameter {
     }
 
     fn get_immediate_value(param: &str, line_number: usize) -> Option<i32> {
-        let conversion = if param.starts_with("0x") {
-            // Remove the '0x'.
-            let mut chars = param.chars();
-            chars.next();
-            chars.next();
-            u32::from_str_radix(chars.as_str(), 16).map(|v| v as i32)
-        } else {
-            param.parse::<i32>()
-        };
+        let number = parse_number(param);
+        if number.is_none() {
+            eprintln!("Invalid number '{}' on line {}", param, line_number);
+        }
+        number
+    }
+}
 
-        match conversion {
-            Ok(number) => Some(number),
-            Err(e) => {
-                eprintln!("Invalid number '{}' on line {}: {}", param, line_number, e);
+impl IRDirective {
+    fn from(directive: &str, params: &str, line_number: usize) -> Option<IRDirective> {
+        match directive {
+            ".global" | ".globl" => Some(IRDirective::Global(Self::label_names(directive, params, line_number)?)),
+            ".extern" => Some(IRDirective::Extern(Self::label_names(directive, params, line_number)?)),
+            ".section" => Self::section(params, line_number),
+            ".text" | ".data" | ".bss" => Some(IRDirective::Section(directive.into(), SectionKind::from_name(directive))),
+            ".word" => {
+                let values = params.split(',').map(|param| IRParameter::from(param, line_number)).collect::<Option<Vec<_>>>()?;
+                match values.iter().find(|value| !matches!(value, IRParameter::Imm(_) | IRParameter::Label(_))) {
+                    Some(value) => {
+                        eprintln!("Invalid word {:?} on line {}", value, line_number);
+                        None
+                    }
+                    None => Some(IRDirective::Word(values)),
+                }
+            }
+            ".space" | ".zero" => match IRParameter::get_immediate_value(params, line_number)? {
+                count if count >= 0 => Some(IRDirective::Space(count as u32)),
+                count => {
+                    eprintln!("Invalid negative size {} on line {}", count, line_number);
+                    None
+                }
+            },
+            _ => {
+                eprintln!("Invalid directive '{}' on line {}", directive, line_number);
                 None
             }
         }
     }
+
+    fn label_names(directive: &str, params: &str, line_number: usize) -> Option<Vec<String>> {
+        let names = params.split(',').map(str::trim).map(String::from).collect::<Vec<_>>();
+        if let Some(name) = names.iter().find(|name| !is_label_name(name)) {
+            eprintln!("Invalid label '{}' in directive '{}' on line {}", name, directive, line_number);
+            return None;
+        }
+        Some(names)
+    }
+
+    /// Parses `.section name` or `.section name, kind`. Without a kind, it is derived from the name.
+    fn section(params: &str, line_number: usize) -> Option<IRDirective> {
+        let (name, kind) = match params.split_once(',') {
+            Some((name, kind)) => (name.trim(), Some(kind.trim())),
+            None => (params, None),
+        };
+
+        let valid = !name.is_empty() && name.chars().all(|c| c.is_alphanumeric() || c == '.' || c == '_');
+        if !valid {
+            eprintln!("Invalid section name '{}' on line {}", name, line_number);
+            return None;
+        }
+
+        let kind = match kind.map(str::to_ascii_lowercase).as_deref() {
+            None => SectionKind::from_name(name),
+            Some("text") => SectionKind::Text,
+            Some("data") => SectionKind::Data,
+            Some("bss") => SectionKind::Bss,
+            Some(kind) => {
+                eprintln!("Invalid section kind '{}' on line {}", kind, line_number);
+                return None;
+            }
+        };
+        Some(IRDirective::Section(name.into(), kind))
+    }
 }
 
 impl IRRegister {
+    pub const ALL: [IRRegister; 6] = [IRRegister::A, IRRegister::B, IRRegister::C, IRRegister::D, IRRegister::IP, IRRegister::SP];
+
+    pub fn from_encoding(encoding: u8) -> Option<IRRegister> {
+        Self::ALL.into_iter().find(|&register| register as u8 == encoding)
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            IRRegister::A => "A",
+            IRRegister::B => "B",
+            IRRegister::C => "C",
+            IRRegister::D => "D",
+            IRRegister::IP => "IP",
+            IRRegister::SP => "SP",
+        }
+    }
+
     fn from(param: &str) -> Option<IRRegister> {
         match param {
             "A" | "a" => Some(IRRegister::A),
@@ -244,10 +385,28 @@ impl IRRegister {
     }
 }
 
+/// Parses decimal or `0x` prefixed hexadecimal numbers, like immediates in assembly.
+pub fn parse_number(number: &str) -> Option<i32> {
+    match number.strip_prefix("0x") {
+        Some(hex) => u32::from_str_radix(hex, 16).ok().map(|value| value as i32),
+        None => number.parse().ok(),
+    }
+}
+
+fn is_label_name(name: &str) -> bool {
+    name.starts_with(char::is_alphabetic) && name.chars().all(char::is_alphanumeric)
+}
+
 pub struct IRTranslationTable {
     command: HashMap<&'static str, IRCommand>,
 }
 
+impl Default for IRTranslationTable {
+    fn default() -> IRTranslationTable {
+        IRTranslationTable::new()
+    }
+}
+
 impl IRTranslationTable {
     pub fn new() -> IRTranslationTable {
         IRTranslationTable {
@@ -255,6 +414,7 @@ impl IRTranslationTable {
         }
     }
 
+    #[allow(clippy::len_zero)]
     pub fn create_intermediate(&self, line: &str, line_number: usize) -> Option<IRLine> {
         // Allow for indents.
         let line = line.trim_start();
@@ -280,14 +440,14 @@ impl IRTranslationTable {
             return None;
         }
 
-        // Handle jump labels.
-        let starts_alphabetic = left.chars().nth(0).unwrap().is_alphabetic();
-        if left.ends_with(':') && starts_alphabetic {
-            let mut label = left.chars();
-            label.next_back(); // Remove the ':'
+        // Handle directives.
+        if left.starts_with(DIRECTIVE_CHAR) {
+            return IRDirective::from(&left.to_ascii_lowercase(), right, line_number).map(|directive| IRLine::Directive(directive, line_number));
+        }
 
-            let label = label.as_str();
-            if label.chars().all(char::is_alphanumeric) {
+        // Handle jump labels.
+        if let Some(label) = left.strip_suffix(':') {
+            if is_label_name(label) {
                 return Some(IRLine::Label(label.into()))
             }
         }
@@ -0,0 +1,108 @@
+use std::{fs, io, path::Path};
+
+use serde::{Deserialize, Serialize};
+
+/// Memory regions of a specific CPU build, read from a JSON file like:
+///
+/// ```json
+/// {
+///   "regions": [
+///     { "name": "rom", "kind": "rom", "start": 1, "size": 1024 },
+///     { "name": "ram", "kind": "ram", "start": 1025, "size": 256 },
+///     { "name": "stack", "kind": "stack", "start": 1281, "size": 64 },
+///     { "name": "display", "kind": "io", "start": 65536, "size": 32 }
+///   ]
+/// }
+/// ```
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct MemoryLayout {
+    pub regions: Vec<Region>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Region {
+    pub name: String,
+    pub kind: RegionKind,
+    pub start: u32,
+    /// Size in words.
+    pub size: u32,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum RegionKind {
+    /// Program memory. The linked program is placed at its start.
+    Rom,
+    Ram,
+    /// SP is initialized to the last word of the stack, because the stack grows towards 0.
+    Stack,
+    /// Addresses which are connected to external components instead of memory.
+    Io,
+}
+
+impl MemoryLayout {
+    pub fn read<P: AsRef<Path>>(path: P) -> io::Result<MemoryLayout> {
+        let content = fs::read_to_string(path)?;
+        let layout: MemoryLayout = serde_json::from_str(&content).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        layout.validate().map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        Ok(layout)
+    }
+
+    /// Checks that there is exactly one ROM region, at most one stack region
+    /// and that no regions overlap.
+    pub fn validate(&self) -> Result<(), String> {
+        let count = |kind| self.regions.iter().filter(|region| region.kind == kind).count();
+        if count(RegionKind::Rom) != 1 {
+            return Err("Memory layout needs exactly one rom region".into());
+        }
+        if count(RegionKind::Stack) > 1 {
+            return Err("Memory layout may have at most one stack region".into());
+        }
+
+        for (i, region) in self.regions.iter().enumerate() {
+            if region.size == 0 || region.start.checked_add(region.size).is_none() {
+                return Err(format!("Memory region '{}' has an invalid size", region.name));
+            }
+            if let Some(other) = self.regions[..i].iter().find(|other| region.overlaps(other)) {
+                return Err(format!("Memory regions '{}' and '{}' overlap", other.name, region.name));
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn rom(&self) -> &Region {
+        self.region_of_kind(RegionKind::Rom).expect("Memory layout without rom region")
+    }
+
+    pub fn region_of_kind(&self, kind: RegionKind) -> Option<&Region> {
+        self.regions.iter().find(|region| region.kind == kind)
+    }
+
+    /// Initial value of SP, if the layout has a stack region.
+    pub fn stack_top(&self) -> Option<u32> {
+        self.region_of_kind(RegionKind::Stack).map(Region::last)
+    }
+
+    pub fn region_at(&self, address: u32) -> Option<&Region> {
+        self.regions.iter().find(|region| region.contains(address))
+    }
+}
+
+impl Region {
+    pub fn end(&self) -> u32 {
+        self.start + self.size
+    }
+
+    pub fn last(&self) -> u32 {
+        self.end() - 1
+    }
+
+    pub fn contains(&self, address: u32) -> bool {
+        self.start <= address && address < self.end()
+    }
+
+    fn overlaps(&self, other: &Region) -> bool {
+        self.start < other.end() && other.start < self.end()
+    }
+}
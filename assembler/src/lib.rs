@@ -1,15 +1,54 @@
-mod ir;
 mod assembler;
+mod linker;
+pub mod archive;
+pub mod arithmetic;
+pub mod blueprint;
+pub mod circuit;
+pub mod console;
+pub mod debug_info;
+pub mod debugger;
+pub mod device;
+pub mod disassembler;
+pub mod emulator;
+pub mod gdb;
+pub mod generator;
+pub mod ir;
+pub mod layout;
+pub mod microarchitecture;
+pub mod object;
+pub mod profiler;
+pub mod rcon;
+pub mod report;
+pub mod rom;
+pub mod signal_policy;
+pub mod timing;
+pub mod trace;
 
 use std::{fs::File, io::{BufReader, BufRead}, path::Path};
 
-use crate::ir::{IR, IRTranslationTable};
+use crate::{debug_info::DebugInfo, ir::{IR, IRTranslationTable}, object::Object};
+
+pub use crate::linker::{link, LinkOptions, Program, PROGRAM_START};
 
 pub fn assemble<P: AsRef<Path>>(input_file: P) -> Vec<u8> {
+    assemble_with_debug_info(input_file).0
+}
+
+/// Assembles the input file and additionally returns the debug information
+/// mapping word addresses to labels and source lines.
+pub fn assemble_with_debug_info<P: AsRef<Path>>(input_file: P) -> (Vec<u8>, DebugInfo) {
+    let program = link(&[assemble_object(input_file)], &LinkOptions::default());
+    (program.binary, program.debug_info)
+}
+
+/// Assembles the input file into an object, which still has to be linked.
+pub fn assemble_object<P: AsRef<Path>>(input_file: P) -> Object {
     let translation = IRTranslationTable::new();
 
+    let source = input_file.as_ref().display().to_string();
     let file = File::open(input_file).expect("Could not open input file");
     let intermediate = IR {
+        source,
         instructions: BufReader::new(file)
             .lines()
             .enumerate()
@@ -17,5 +56,5 @@ pub fn assemble<P: AsRef<Path>>(input_file: P) -> Vec<u8> {
             .collect(),
     };
 
-    return assembler::assemble(intermediate);
+    assembler::assemble(intermediate)
 }
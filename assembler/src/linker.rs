@@ -0,0 +1,242 @@
+use std::collections::HashMap;
+
+use crate::{
+    assembler::{HALT_INSTRUCTION, MOV_REG_IMM_INSTRUCTION},
+    debug_info::{DebugInfo, LineInfo, Symbol},
+    ir::IRRegister,
+    layout::{MemoryLayout, RegionKind},
+    object::{Object, SectionKind},
+};
+
+/// Address of the first word of a linked program. Address 0 cannot be
+/// used in-game, because a signal with value 0 is not present on the wire.
+pub const PROGRAM_START: u32 = 1;
+
+#[derive(Default, Debug, Clone)]
+pub struct LinkOptions {
+    /// Remove all sections which are not reachable from the entry section
+    /// through jumps, calls or label references.
+    pub gc_sections: bool,
+    /// Memory regions the program is placed into and checked against.
+    /// Without a layout the program starts at `PROGRAM_START` and is not checked.
+    pub layout: Option<MemoryLayout>,
+}
+
+/// Location of a section: index of the object and index of the section in the object.
+type SectionId = (usize, usize);
+
+struct SymbolTable<'a> {
+    objects: &'a [Object],
+    globals: HashMap<&'a str, (SectionId, u32)>,
+}
+
+impl<'a> SymbolTable<'a> {
+    fn new(objects: &'a [Object], errors: &mut Vec<String>) -> SymbolTable<'a> {
+        let mut globals: HashMap<&str, (SectionId, u32)> = HashMap::new();
+        for (object_index, object) in objects.iter().enumerate() {
+            for (section_index, section) in object.sections.iter().enumerate() {
+                for symbol in section.symbols.iter().filter(|symbol| symbol.global) {
+                    let location = ((object_index, section_index), symbol.offset);
+                    if let Some(((other, _), _)) = globals.insert(&symbol.name, location) {
+                        errors.push(format!("Duplicate symbol '{}' defined in '{}' and '{}'", symbol.name, objects[other].source, object.source));
+                    }
+                }
+            }
+        }
+
+        SymbolTable { objects, globals }
+    }
+
+    /// Resolves a symbol referenced by the given object. Labels of the object
+    /// itself take precedence over global labels.
+    fn resolve(&self, object_index: usize, name: &str) -> Option<(SectionId, u32)> {
+        match self.objects[object_index].symbol(name) {
+            Some((section_index, symbol)) => Some(((object_index, section_index), symbol.offset)),
+            None => self.globals.get(name).copied(),
+        }
+    }
+
+    /// Marks all sections reachable from the entry section.
+    fn reachable_sections(&self, entry: Option<SectionId>) -> Vec<Vec<bool>> {
+        let mut reachable = self.objects.iter().map(|object| vec![false; object.sections.len()]).collect::<Vec<_>>();
+        let mut pending = entry.into_iter().collect::<Vec<_>>();
+
+        while let Some((object_index, section_index)) = pending.pop() {
+            if reachable[object_index][section_index] {
+                continue;
+            }
+            reachable[object_index][section_index] = true;
+
+            for relocation in &self.objects[object_index].sections[section_index].relocations {
+                if let Some((target, _)) = self.resolve(object_index, &relocation.symbol) {
+                    pending.push(target);
+                }
+            }
+        }
+
+        reachable
+    }
+}
+
+/// Result of linking objects.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Program {
+    /// Rom content, starting at `start`.
+    pub binary: Vec<u8>,
+    pub start: u32,
+    /// Initial ram content of the data sections, starting at `data_start`. Only used
+    /// if the memory layout has a ram region, otherwise data is placed in `binary`.
+    pub data: Vec<u8>,
+    pub data_start: u32,
+    pub debug_info: DebugInfo,
+}
+
+/// Links the objects into a single program. The sections are placed in the order
+/// of the objects, text first, then data and bss. The first text section of the
+/// first object is the entry point of the program.
+///
+/// Panics with a list of all undefined and duplicate symbols if linking fails.
+pub fn link(objects: &[Object], options: &LinkOptions) -> Program {
+    let mut errors = Vec::new();
+    let symbols = SymbolTable::new(objects, &mut errors);
+    let entry = objects.first().and_then(|object| object.sections.iter().position(|section| section.kind == SectionKind::Text)).map(|section_index| (0, section_index));
+    let keep = if options.gc_sections {
+        symbols.reachable_sections(entry)
+    } else {
+        objects.iter().map(|object| vec![true; object.sections.len()]).collect()
+    };
+
+    let layout = options.layout.as_ref();
+    let ram = layout.and_then(|layout| layout.region_of_kind(RegionKind::Ram));
+    let start = layout.map_or(PROGRAM_START, |layout| layout.rom().start);
+    let mut rom = Vec::new();
+    let mut data = Vec::new();
+
+    // Initialize SP before anything else is executed.
+    if let Some(stack_top) = layout.and_then(MemoryLayout::stack_top) {
+        rom.push(((IRRegister::SP as u32) << 8) | MOV_REG_IMM_INSTRUCTION as u32);
+        rom.push(stack_top);
+    }
+
+    // HALT is added after the entry section if it is not present,
+    // so execution does not run into the next section.
+    let entry_halt = entry.is_none_or(|(object_index, section_index)| !objects[object_index].sections[section_index].ends_with_halt);
+    if entry.is_none() {
+        rom.push(HALT_INSTRUCTION as u32);
+    }
+
+    // Place the sections one after another, grouped by their kind.
+    let sections_of_kind = |kind| {
+        objects.iter().enumerate()
+            .flat_map(|(object_index, object)| (0..object.sections.len()).map(move |section_index| (object_index, section_index)))
+            .filter(|&(object_index, section_index)| keep[object_index][section_index] && objects[object_index].sections[section_index].kind == kind)
+            .collect::<Vec<_>>()
+    };
+    let text = sections_of_kind(SectionKind::Text);
+    let variables = [sections_of_kind(SectionKind::Data), sections_of_kind(SectionKind::Bss)].concat();
+
+    let mut bases = HashMap::new();
+    let mut place = |sections: &[SectionId], mut location: u32| {
+        for &id in sections {
+            bases.insert(id, location);
+            location += objects[id.0].sections[id.1].size();
+            if Some(id) == entry && entry_halt {
+                location += 1;
+            }
+        }
+        location
+    };
+    let rom_end = place(&text, start + rom.len() as u32);
+    let data_start = ram.map_or(rom_end, |ram| ram.start);
+    let data_end = place(&variables, data_start);
+
+    if let Some(layout) = layout {
+        let rom_region = layout.rom();
+        let rom_size = rom_end - start;
+        if rom_size > rom_region.size {
+            errors.push(format!("Program needs {} words, but rom region '{}' only has {} words", rom_size, rom_region.name, rom_region.size));
+        }
+        match ram {
+            Some(ram) if data_end - ram.start > ram.size => {
+                errors.push(format!("Data needs {} words, but ram region '{}' only has {} words", data_end - ram.start, ram.name, ram.size));
+            }
+            None if !variables.is_empty() => errors.push("Data and bss sections need a ram region in the memory layout".into()),
+            _ => {}
+        }
+    }
+
+    let mut debug_info = DebugInfo {
+        files: objects.iter().map(|object| object.source.clone()).collect(),
+        ..DebugInfo::default()
+    };
+
+    for &id @ (object_index, _) in text.iter().chain(&variables) {
+        let object = &objects[object_index];
+        let section = &object.sections[id.1];
+        let base = bases[&id];
+
+        let mut section_words = section.words.clone();
+        for relocation in &section.relocations {
+            let target = match symbols.resolve(object_index, &relocation.symbol) {
+                Some((target_section, offset)) => bases[&target_section] + offset,
+                None => {
+                    errors.push(format!("Undefined symbol '{}' referenced in '{}'", relocation.symbol, object.source));
+                    continue;
+                }
+            };
+
+            let address = base + relocation.offset;
+            let word = &mut section_words[relocation.offset as usize];
+            match relocation.kind.patch(*word, address, target) {
+                Some(patched) => *word = patched,
+                None => errors.push(format!("Symbol '{}' is out of jump range in '{}'", relocation.symbol, object.source)),
+            }
+        }
+
+        if let Some(layout) = layout {
+            for operand in &section.memory_operands {
+                if layout.region_at(operand.address as u32).is_none() {
+                    errors.push(format!("Memory address {} on line {} of '{}' is outside of all memory regions", operand.address, operand.line, object.source));
+                }
+            }
+        }
+
+        match section.kind {
+            SectionKind::Text => rom.extend(section_words),
+            SectionKind::Data if ram.is_some() => data.extend(section_words),
+            SectionKind::Data => rom.extend(section_words),
+            // Bss only reserves addresses.
+            SectionKind::Bss => {}
+        }
+        if Some(id) == entry && entry_halt {
+            rom.push(HALT_INSTRUCTION as u32);
+        }
+
+        debug_info.symbols.extend(section.symbols.iter().map(|symbol| Symbol {
+            name: symbol.name.clone(),
+            address: base + symbol.offset,
+        }));
+        debug_info.lines.extend(section.lines.iter().map(|line| LineInfo {
+            file: object_index,
+            line: line.line,
+            start: base + line.start,
+            end: base + line.end,
+            data: line.data,
+        }));
+    }
+
+    if !errors.is_empty() {
+        for error in &errors {
+            eprintln!("{}", error);
+        }
+        panic!("Linking failed: {}", errors.join("; "));
+    }
+
+    Program {
+        binary: rom.into_iter().flat_map(u32::to_be_bytes).collect(),
+        start,
+        data: data.into_iter().flat_map(u32::to_be_bytes).collect(),
+        data_start,
+        debug_info,
+    }
+}
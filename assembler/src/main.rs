@@ -1,22 +1,84 @@
-use std::fs;
+use std::{
+    env,
+    fs::{self, File},
+    io::{self, BufRead, BufWriter, Write},
+    net::TcpListener,
+    path::Path,
+    process,
+};
 
-use clap::{App, Arg};
+use clap::{App, AppSettings, Arg, ArgMatches};
 
-use lib::assemble;
+use lib::{
+    archive::{self, Archive, ARCHIVE_EXTENSION},
+    assemble_object,
+    blueprint::Blueprint,
+    circuit::Circuit,
+    console::{self, Anchor},
+    debug_info::DebugInfo,
+    debugger::Debugger,
+    device,
+    disassembler::disassemble,
+    emulator::{Checks, Emulator},
+    gdb::GdbServer,
+    generator,
+    profiler::Profiler,
+    rcon::RconClient,
+    report::Report,
+    rom::{self, RomPlan},
+    signal_policy::SignalPolicy,
+    timing::{self, TimingModel},
+    trace::{self, Tracer},
+    ir::parse_number,
+    layout::{MemoryLayout, RegionKind},
+    link,
+    object::{Object, OBJECT_EXTENSION},
+    LinkOptions, Program, PROGRAM_START,
+};
 
 const DEFAULT_OUTPUT: &str = "out.bin";
+const DEFAULT_MAX_STEPS: &str = "1000000";
+const DEFAULT_GDB_PORT: &str = "1234";
+const DEFAULT_GAME_SPEED: &str = "1";
+const DEFAULT_TICKS: &str = "1";
+const DEFAULT_CHECK_TICKS: &str = "100";
+const DEFAULT_ROM_SLOTS: &str = "1";
+const DEFAULT_RCON_ADDRESS: &str = "127.0.0.1:27015";
+/// Environment variable with the RCON password, if it is not given as argument.
+const RCON_PASSWORD_VARIABLE: &str = "FACTORIO_RCON_PASSWORD";
+
+enum OutputFormat {
+    Binary,
+    /// Console command loading the program into the rom combinators around the anchor.
+    Lua(Anchor),
+    /// Rom constant combinators.
+    Blueprint,
+}
 
 struct Arguments {
-    input_file: String,
+    input_files: Vec<String>,
     output_file: String,
+    debug_info_file: Option<String>,
+    data_file: Option<String>,
+    format: OutputFormat,
+    /// Words per constant combinator of the rom.
+    rom_slots: usize,
+    /// Print a summary of the linked program, estimating ticks with the timing model.
+    report: Option<TimingModel>,
+    object_only: bool,
+    archive: bool,
+    link_options: LinkOptions,
 }
 
-fn parse_arguments() -> Option<Arguments> {
-    let matches = App::new("JP Factorio Assembler")
+fn app() -> App<'static> {
+    App::new("JP Factorio Assembler")
         .version("0.1.0")
+        .setting(AppSettings::ArgsNegateSubcommands)
+        .setting(AppSettings::SubcommandsNegateReqs)
         .arg(
             Arg::new("input-file")
-                .help("Assembly file that is going to be assembled")
+                .help("Assembly, object or archive files that are going to be assembled and linked")
+                .multiple_values(true)
                 .required(true),
         )
         .arg(
@@ -26,25 +88,906 @@ fn parse_arguments() -> Option<Arguments> {
                 .default_value(DEFAULT_OUTPUT)
                 .help("Output file to which the assembled binary output is written"),
         )
-        .get_matches();
+        .arg(
+            Arg::new("object")
+                .short('c')
+                .help("Only assemble the input file into an object file without linking"),
+        )
+        .arg(
+            Arg::new("archive")
+                .long("archive")
+                .conflicts_with("object")
+                .help("Bundle the input files into an archive without linking"),
+        )
+        .arg(
+            Arg::new("gc-sections")
+                .long("gc-sections")
+                .help("Remove sections that are not reachable from the entry section"),
+        )
+        .arg(
+            Arg::new("layout")
+                .short('m')
+                .long("layout")
+                .value_name("FILE")
+                .help("JSON file declaring the rom, ram, stack and io memory regions"),
+        )
+        .arg(
+            Arg::new("data-output")
+                .short('d')
+                .long("data-output")
+                .value_name("FILE")
+                .help("Output file for the initial ram content of data sections, if the layout has a ram region"),
+        )
+        .arg(
+            Arg::new("debug-info")
+                .short('g')
+                .long("debug-info")
+                .value_name("FILE")
+                .help("Write a JSON file mapping labels and source lines to word addresses"),
+        )
+        .arg(
+            Arg::new("format")
+                .long("format")
+                .value_name("FORMAT")
+                .possible_values(["binary", "lua", "blueprint"])
+                .default_value("binary")
+                .help("Output format: binary, lua (/c console command writing the program into the rom combinators) or blueprint (rom constant combinators)"),
+        )
+        .arg(
+            Arg::new("rom-slots")
+                .long("rom-slots")
+                .value_name("N")
+                .default_value(DEFAULT_ROM_SLOTS)
+                .help("Words packed into each constant combinator of the rom blueprint or lua command, up to 20"),
+        )
+        .arg(
+            Arg::new("report")
+                .long("report")
+                .help("Print words per symbol, instructions per opcode, rom combinators and ticks per straight-line block"),
+        )
+        .arg(
+            Arg::new("timing")
+                .long("timing")
+                .value_name("FILE")
+                .requires("report")
+                .help("JSON file with the ticks per instruction, memory access and additional word for the report"),
+        )
+        .arg(
+            Arg::new("rom-tag")
+                .long("rom-tag")
+                .value_name("TEXT")
+                .help("Map tag on the first rom combinator the lua command writes to, instead of the combinator under the cursor"),
+        )
+        .subcommand(
+            App::new("disasm")
+                .about("Turns a binary back into assembly")
+                .arg(
+                    Arg::new("input-file")
+                        .help("Binary file that is going to be disassembled")
+                        .required(true),
+                )
+                .arg(
+                    Arg::new("output-file")
+                        .short('o')
+                        .value_name("FILE")
+                        .help("Output file for the assembly (default: stdout)"),
+                )
+                .arg(
+                    Arg::new("start")
+                        .long("start")
+                        .value_name("ADDRESS")
+                        .help("Address of the first word of the binary"),
+                )
+                .arg(
+                    Arg::new("debug-info")
+                        .short('g')
+                        .long("debug-info")
+                        .value_name("FILE")
+                        .help("Debug info file to take label names from"),
+                )
+                .arg(
+                    Arg::new("blueprint")
+                        .long("blueprint")
+                        .help("The input file is a rom blueprint string instead of a binary"),
+                )
+                .arg(
+                    Arg::new("rom-slots")
+                        .long("rom-slots")
+                        .value_name("N")
+                        .default_value(DEFAULT_ROM_SLOTS)
+                        .help("Words packed into each constant combinator of the rom blueprint (with --blueprint)"),
+                ),
+        )
+        .subcommand(
+            App::new("run")
+                .about("Executes a program in the emulator and prints the final registers and flags")
+                .arg(
+                    Arg::new("input-file")
+                        .help("Assembly, object or binary file that is going to be executed")
+                        .required(true),
+                )
+                .arg(
+                    Arg::new("start")
+                        .long("start")
+                        .value_name("ADDRESS")
+                        .help("Address the binary is loaded to and executed from"),
+                )
+                .arg(
+                    Arg::new("max-steps")
+                        .long("max-steps")
+                        .value_name("N")
+                        .default_value(DEFAULT_MAX_STEPS)
+                        .help("Maximum number of instructions that are executed if the program does not halt"),
+                )
+                .arg(
+                    Arg::new("trace")
+                        .long("trace")
+                        .value_name("FILE")
+                        .help("Write every executed instruction with its register, flag and memory changes"),
+                )
+                .arg(
+                    Arg::new("timing")
+                        .long("timing")
+                        .value_name("FILE")
+                        .help("JSON file with the ticks per instruction, memory access and additional word"),
+                )
+                .arg(
+                    Arg::new("game-speed")
+                        .long("game-speed")
+                        .value_name("SPEED")
+                        .default_value(DEFAULT_GAME_SPEED)
+                        .help("Value of game.speed used to convert ticks into real time"),
+                )
+                .arg(
+                    Arg::new("profile")
+                        .long("profile")
+                        .help("Print the instructions and ticks spent per function and source line"),
+                )
+                .arg(
+                    Arg::new("folded-stacks")
+                        .long("folded-stacks")
+                        .value_name("FILE")
+                        .help("Write the ticks per call stack in the folded format of flamegraph tools"),
+                )
+                .arg(device_arg())
+                .arg(
+                    Arg::new("check")
+                        .long("check")
+                        .help("Stop with a fault on division by zero, stack errors, uninitialized reads, executing data, jumps outside of the program or reaching --max-steps"),
+                )
+                .arg(
+                    Arg::new("layout")
+                        .long("layout")
+                        .value_name("FILE")
+                        .help("Memory layout the assembly or object file is linked with. Its stack region is checked with --check"),
+                ),
+        )
+        .subcommand(
+            App::new("debug")
+                .about("Executes a program interactively with breakpoints, stepping and memory inspection")
+                .arg(
+                    Arg::new("input-file")
+                        .help("Assembly, object or binary file that is going to be debugged")
+                        .required(true),
+                )
+                .arg(
+                    Arg::new("start")
+                        .long("start")
+                        .value_name("ADDRESS")
+                        .help("Address the binary is loaded to and executed from"),
+                )
+                .arg(
+                    Arg::new("debug-info")
+                        .short('g')
+                        .long("debug-info")
+                        .value_name("FILE")
+                        .help("Debug info file for labels and source lines of a binary"),
+                )
+                .arg(device_arg()),
+        )
+        .subcommand(
+            App::new("gdb")
+                .about("Executes a program under control of a GDB remote protocol client")
+                .arg(
+                    Arg::new("input-file")
+                        .help("Assembly, object or binary file that is going to be debugged")
+                        .required(true),
+                )
+                .arg(
+                    Arg::new("start")
+                        .long("start")
+                        .value_name("ADDRESS")
+                        .help("Address the binary is loaded to and executed from"),
+                )
+                .arg(
+                    Arg::new("port")
+                        .long("port")
+                        .value_name("PORT")
+                        .default_value(DEFAULT_GDB_PORT)
+                        .help("Local TCP port the client connects to"),
+                )
+                .arg(device_arg()),
+        )
+        .subcommand(
+            App::new("trace-diff")
+                .about("Finds the first difference between two traces written by run --trace")
+                .arg(Arg::new("left").help("First trace file").required(true))
+                .arg(Arg::new("right").help("Second trace file").required(true)),
+        )
+        .subcommand(
+            App::new("simulate")
+                .about("Simulates the combinators of a blueprint and prints their output signals")
+                .arg(Arg::new("input-file").help("File with a blueprint string").required(true))
+                .arg(
+                    Arg::new("ticks")
+                        .long("ticks")
+                        .value_name("N")
+                        .default_value(DEFAULT_TICKS)
+                        .help("Number of ticks that are simulated"),
+                ),
+        )
+        .subcommand(
+            App::new("check-signals")
+                .about("Checks that the wires of a blueprint only carry the signals allowed by a signal policy")
+                .arg(Arg::new("input-file").help("File with a blueprint string").required(true))
+                .arg(
+                    Arg::new("policy")
+                        .long("policy")
+                        .value_name("FILE")
+                        .help("JSON file with the signals of every bus (default: the buses of MicroArchitecture.md)"),
+                )
+                .arg(
+                    Arg::new("ticks")
+                        .long("ticks")
+                        .value_name("N")
+                        .default_value(DEFAULT_CHECK_TICKS)
+                        .help("Number of ticks that are simulated in addition to the static analysis"),
+                ),
+        )
+        .subcommand(
+            App::new("generate")
+                .about("Generates a part of the CPU as blueprint string from the instruction set of the assembler")
+                .arg(
+                    Arg::new("part")
+                        .help("Part of the CPU: decoder (deciders raising Yellow for every instruction type) or ram (memory bank)")
+                        .possible_values(["decoder", "ram"])
+                        .required(true),
+                )
+                .arg(
+                    Arg::new("start")
+                        .long("start")
+                        .value_name("ADDRESS")
+                        .conflicts_with("layout")
+                        .help("Address of the first word of the memory bank, 1 if not given"),
+                )
+                .arg(
+                    Arg::new("size")
+                        .long("size")
+                        .value_name("WORDS")
+                        .conflicts_with("layout")
+                        .help("Number of words of the memory bank"),
+                )
+                .arg(
+                    Arg::new("layout")
+                        .short('m')
+                        .long("layout")
+                        .value_name("FILE")
+                        .help("Memory layout, the memory bank is its ram region"),
+                )
+                .arg(
+                    Arg::new("data")
+                        .long("data")
+                        .value_name("FILE")
+                        .help("Assembly, object or binary file whose data initializes the memory bank (the whole program without a ram region)"),
+                )
+                .arg(
+                    Arg::new("output-file")
+                        .short('o')
+                        .long("output")
+                        .value_name("FILE")
+                        .help("Output file for the blueprint string, printed if not given"),
+                ),
+        )
+        .subcommand(
+            App::new("upload")
+                .about("Loads a program into the rom combinators of a running Factorio server over RCON")
+                .arg(
+                    Arg::new("input-file")
+                        .help("Assembly, object or binary file that is going to be loaded")
+                        .required(true),
+                )
+                .arg(
+                    Arg::new("rom-tag")
+                        .long("rom-tag")
+                        .value_name("TEXT")
+                        .required(true)
+                        .help("Map tag on the first rom combinator"),
+                )
+                .arg(
+                    Arg::new("address")
+                        .long("address")
+                        .value_name("HOST:PORT")
+                        .default_value(DEFAULT_RCON_ADDRESS)
+                        .help("RCON address of the server"),
+                )
+                .arg(
+                    Arg::new("password")
+                        .long("password")
+                        .value_name("PASSWORD")
+                        .help("RCON password of the server (default: $FACTORIO_RCON_PASSWORD)"),
+                )
+                .arg(
+                    Arg::new("rom-slots")
+                        .long("rom-slots")
+                        .value_name("N")
+                        .default_value(DEFAULT_ROM_SLOTS)
+                        .help("Words packed into each rom combinator, up to 20"),
+                )
+                .arg(
+                    Arg::new("yes")
+                        .short('y')
+                        .long("yes")
+                        .help("Overwrite the rom combinators without asking for confirmation"),
+                )
+                .arg(
+                    Arg::new("layout")
+                        .short('m')
+                        .long("layout")
+                        .value_name("FILE")
+                        .help("JSON file declaring the rom, ram, stack and io memory regions"),
+                ),
+        )
+}
+
+/// `--device` of the subcommands which execute a program.
+fn device_arg() -> Arg<'static> {
+    Arg::new("device")
+        .long("device")
+        .value_name("KIND@ADDRESS")
+        .multiple_occurrences(true)
+        .help("Attach a device: lamps@ADDRESS:WIDTHxHEIGHT, number@ADDRESS, keyboard@ADDRESS[:FILE] or timer@ADDRESS")
+}
 
-    match (matches.value_of("input-file"), matches.value_of("output-file")) {
-        (Some(input_file), output_file) => Some(Arguments {
-            input_file: input_file.into(),
-            output_file: output_file.unwrap_or(DEFAULT_OUTPUT).into(),
+fn parse_arguments(matches: &ArgMatches) -> Option<Arguments> {
+    let object_only = matches.is_present("object");
+    let archive = matches.is_present("archive");
+    let format = match matches.value_of("format") {
+        Some("lua") => OutputFormat::Lua(match matches.value_of("rom-tag") {
+            Some(tag) => Anchor::Tag(tag.into()),
+            None => Anchor::Player,
         }),
-        _ => None,
+        Some("blueprint") => OutputFormat::Blueprint,
+        _ => OutputFormat::Binary,
+    };
+    let input_files = matches.values_of("input-file")?.map(String::from).collect::<Vec<_>>();
+    if object_only && input_files.len() != 1 {
+        return None;
     }
+
+    // Objects are named after their source by default.
+    let output_file = match matches.value_of("output-file") {
+        Some(_) if object_only && matches.occurrences_of("output-file") == 0 => {
+            Path::new(&input_files[0]).with_extension(OBJECT_EXTENSION).display().to_string()
+        }
+        Some(_) if archive && matches.occurrences_of("output-file") == 0 => {
+            Path::new(DEFAULT_OUTPUT).with_extension(ARCHIVE_EXTENSION).display().to_string()
+        }
+        Some(_) if matches.occurrences_of("output-file") == 0 && !matches!(format, OutputFormat::Binary) => {
+            let extension = if matches!(format, OutputFormat::Lua(_)) { "lua" } else { "txt" };
+            Path::new(DEFAULT_OUTPUT).with_extension(extension).display().to_string()
+        }
+        output_file => output_file.unwrap_or(DEFAULT_OUTPUT).into(),
+    };
+
+    Some(Arguments {
+        input_files,
+        output_file,
+        debug_info_file: matches.value_of("debug-info").map(String::from),
+        data_file: matches.value_of("data-output").map(String::from),
+        format,
+        rom_slots: parse_rom_slots(matches)?,
+        report: matches.is_present("report").then(|| match matches.value_of("timing") {
+            Some(timing_file) => TimingModel::read(timing_file).expect("Could not read timing file"),
+            None => TimingModel::default(),
+        }),
+        object_only,
+        archive,
+        link_options: LinkOptions {
+            gc_sections: matches.is_present("gc-sections"),
+            layout: matches.value_of("layout").map(|file| MemoryLayout::read(file).expect("Could not read memory layout file")),
+        },
+    })
+}
+
+fn parse_rom_slots(matches: &ArgMatches) -> Option<usize> {
+    matches.value_of("rom-slots")?.parse().ok().filter(|slots| (1..=rom::MAX_SLOTS).contains(slots))
+}
+
+fn has_extension(file: &str, extension: &str) -> bool {
+    Path::new(file).extension().is_some_and(|file_extension| file_extension == extension)
 }
 
 fn main() {
-    let args = if let Some(file) = parse_arguments() {
+    let matches = app().get_matches();
+    match matches.subcommand() {
+        Some(("disasm", matches)) => disassemble_command(matches),
+        Some(("run", matches)) => run_command(matches),
+        Some(("debug", matches)) => debug_command(matches),
+        Some(("gdb", matches)) => gdb_command(matches),
+        Some(("upload", matches)) => upload_command(matches),
+        Some(("trace-diff", matches)) => trace_diff_command(matches),
+        Some(("simulate", matches)) => simulate_command(matches),
+        Some(("check-signals", matches)) => check_signals_command(matches),
+        Some(("generate", matches)) => generate_command(matches),
+        _ => assemble_command(&matches),
+    }
+}
+
+fn parse_start(matches: &ArgMatches) -> Option<u32> {
+    match matches.value_of("start").map(parse_address) {
+        Some(Some(start)) => Some(start),
+        None => Some(PROGRAM_START),
+        Some(None) => {
+            eprintln!("Invalid start address '{}'", matches.value_of("start").unwrap());
+            None
+        }
+    }
+}
+
+/// Decimal or `0x` prefixed hexadecimal address or size, like the numbers in assembly.
+fn parse_address(value: &str) -> Option<u32> {
+    parse_number(value).and_then(|number| u32::try_from(number).ok())
+}
+
+/// Binaries are used as they are, assembly and object files are linked with `options`.
+fn load_program(input_file: &str, start: u32, options: &LinkOptions) -> Program {
+    let object = if has_extension(input_file, OBJECT_EXTENSION) {
+        Object::read(input_file).expect("Could not read object file")
+    } else if has_extension(input_file, "asm") {
+        assemble_object(input_file)
+    } else {
+        let binary = fs::read(input_file).expect("Could not read input file");
+        return Program {
+            binary,
+            start,
+            data: Vec::new(),
+            data_start: start,
+            debug_info: DebugInfo::default(),
+        };
+    };
+    link(&[object], options)
+}
+
+fn disassemble_command(matches: &ArgMatches) {
+    let start = if let Some(start) = parse_start(matches) {
+        start
+    } else {
+        return;
+    };
+
+    let input_file = matches.value_of("input-file").unwrap();
+    let binary = if matches.is_present("blueprint") {
+        let slots = if let Some(slots) = parse_rom_slots(matches) {
+            slots
+        } else {
+            eprintln!("Invalid number of rom slots, a constant combinator has 1 to {} slots", rom::MAX_SLOTS);
+            return;
+        };
+        let blueprint = Blueprint::read(input_file).expect("Could not read blueprint file");
+        match RomPlan::unpack(&blueprint, start, slots) {
+            Ok(rom) => rom.binary(),
+            Err(e) => {
+                eprintln!("{}", e);
+                return;
+            }
+        }
+    } else {
+        fs::read(input_file).expect("Could not read input file")
+    };
+    let debug_info = matches.value_of("debug-info").map(|file| DebugInfo::read(file).expect("Could not read debug info file"));
+    let assembly = disassemble(&binary, start, debug_info.as_ref());
+
+    match matches.value_of("output-file") {
+        Some(output_file) => fs::write(output_file, assembly).expect("Could not create output file"),
+        None => print!("{}", assembly),
+    }
+}
+
+/// Emulator with the program loaded and the devices of the `--device` arguments attached.
+/// If stdin is not free because it is read for commands, a keyboard needs a file.
+fn create_emulator(matches: &ArgMatches, program: &Program, stdin_free: bool) -> Option<Emulator> {
+    let mut emulator = Emulator::with_program(program);
+    for description in matches.values_of("device").into_iter().flatten() {
+        if !stdin_free && device::reads_stdin(description) {
+            eprintln!("The keyboard '{}' would read the commands from stdin, use keyboard@ADDRESS:FILE", description);
+            return None;
+        }
+        match device::parse_device(description) {
+            Ok((address, device)) => emulator.attach(address, device),
+            Err(e) => {
+                eprintln!("{}", e);
+                return None;
+            }
+        }
+    }
+    Some(emulator)
+}
+
+fn run_command(matches: &ArgMatches) {
+    let start = if let Some(start) = parse_start(matches) {
+        start
+    } else {
+        return;
+    };
+    let (max_steps, game_speed) = match (matches.value_of("max-steps").unwrap().parse(), matches.value_of("game-speed").unwrap().parse::<f64>()) {
+        (Ok(max_steps), Ok(game_speed)) if game_speed > 0.0 => (max_steps, game_speed),
+        _ => {
+            eprintln!("Invalid argument(s). Try --help for more information.");
+            return;
+        }
+    };
+
+    let options = LinkOptions {
+        layout: matches.value_of("layout").map(|file| MemoryLayout::read(file).expect("Could not read memory layout file")),
+        ..LinkOptions::default()
+    };
+    let program = load_program(matches.value_of("input-file").unwrap(), start, &options);
+    let mut emulator = if let Some(emulator) = create_emulator(matches, &program, true) {
+        emulator
+    } else {
+        return;
+    };
+    if matches.is_present("check") {
+        let stack = options.layout.as_ref().and_then(|layout| layout.region_of_kind(RegionKind::Stack));
+        emulator.set_checks(Checks::all(&program, stack.map(|stack| stack.start as i32..=stack.last() as i32)));
+    }
+    if let Some(timing_file) = matches.value_of("timing") {
+        emulator.set_timing(TimingModel::read(timing_file).expect("Could not read timing file"));
+    }
+
+    let mut tracer = matches.value_of("trace").map(|trace_file| {
+        let file = File::create(trace_file).expect("Could not create trace file");
+        Tracer::new(BufWriter::new(file), &emulator)
+    });
+    let folded_stacks_file = matches.value_of("folded-stacks");
+    let mut profiler = (matches.is_present("profile") || folded_stacks_file.is_some()).then(|| Profiler::new(program.debug_info.clone(), program.start as i32));
+
+    let result = emulator.run_with(max_steps, |emulator, step| {
+        if let Some(tracer) = &mut tracer {
+            tracer.record(emulator, step).expect("Could not write trace file");
+        }
+        if let Some(profiler) = &mut profiler {
+            profiler.record(emulator, step);
+        }
+    });
+    if let Some(tracer) = tracer {
+        tracer.into_inner().flush().expect("Could not write trace file");
+    }
+
+    match result {
+        Ok(true) => println!("Halted after {} instructions", emulator.steps),
+        Ok(false) => println!("[Warning] Did not halt within {} instructions", max_steps),
+        Err(fault) => match program.debug_info.location(fault.address() as u32) {
+            Some(location) => println!("{} ({}) after {} instructions", fault, location, emulator.steps),
+            None => println!("{} after {} instructions", fault, emulator.steps),
+        },
+    }
+    println!("{} ticks ({:.2} s at game.speed {})", emulator.ticks, timing::seconds(emulator.ticks, game_speed), game_speed);
+    println!("{}", emulator);
+
+    for (address, device) in emulator.devices() {
+        if let Some(state) = device.render() {
+            println!("Device at {}:\n{}", address, state);
+        }
+    }
+
+    if let Some(profiler) = profiler {
+        if matches.is_present("profile") {
+            println!("\n{}", profiler.report());
+        }
+        if let Some(folded_stacks_file) = folded_stacks_file {
+            fs::write(folded_stacks_file, profiler.folded_stacks() + "\n").expect("Could not create folded stacks file");
+        }
+    }
+}
+
+fn debug_command(matches: &ArgMatches) {
+    let start = if let Some(start) = parse_start(matches) {
+        start
+    } else {
+        return;
+    };
+
+    let mut program = load_program(matches.value_of("input-file").unwrap(), start, &LinkOptions::default());
+    if let Some(file) = matches.value_of("debug-info") {
+        program.debug_info = DebugInfo::read(file).expect("Could not read debug info file");
+    }
+    let mut debugger = if let Some(emulator) = create_emulator(matches, &program, false) {
+        Debugger::new(emulator, &program)
+    } else {
+        return;
+    };
+
+    println!("Type 'help' for a list of commands.");
+    let mut lines = io::stdin().lock().lines();
+    loop {
+        print!("(debug) ");
+        io::stdout().flush().expect("Could not write to stdout");
+        let line = match lines.next() {
+            Some(line) => line.expect("Could not read from stdin"),
+            None => break,
+        };
+        match debugger.execute(&line) {
+            Some(output) if output.is_empty() => {}
+            Some(output) => println!("{}", output),
+            None => break,
+        }
+    }
+}
+
+fn gdb_command(matches: &ArgMatches) {
+    let start = if let Some(start) = parse_start(matches) {
+        start
+    } else {
+        return;
+    };
+
+    let program = load_program(matches.value_of("input-file").unwrap(), start, &LinkOptions::default());
+    let mut server = if let Some(emulator) = create_emulator(matches, &program, true) {
+        GdbServer::new(emulator)
+    } else {
+        return;
+    };
+
+    let address = format!("127.0.0.1:{}", matches.value_of("port").unwrap());
+    let listener = TcpListener::bind(&address).expect("Could not listen on port");
+    println!("Waiting for a GDB client on {}", address);
+    server.serve(&listener).expect("Connection to GDB client failed");
+}
+
+fn upload_command(matches: &ArgMatches) {
+    let password = match matches.value_of("password").map(String::from).or_else(|| env::var(RCON_PASSWORD_VARIABLE).ok()) {
+        Some(password) => password,
+        None => {
+            eprintln!("RCON password is missing, use --password or set {}", RCON_PASSWORD_VARIABLE);
+            return;
+        }
+    };
+    let options = LinkOptions {
+        layout: matches.value_of("layout").map(|file| MemoryLayout::read(file).expect("Could not read memory layout file")),
+        ..LinkOptions::default()
+    };
+    let program = load_program(matches.value_of("input-file").unwrap(), PROGRAM_START, &options);
+    let slots = if let Some(slots) = parse_rom_slots(matches) {
+        slots
+    } else {
+        eprintln!("Invalid number of rom slots, a constant combinator has 1 to {} slots", rom::MAX_SLOTS);
+        return;
+    };
+    let rom = RomPlan::new(&program.binary, program.start, slots);
+    let tag = matches.value_of("rom-tag").unwrap();
+    let command = console::rom_loader(&rom, &Anchor::Tag(tag.into()));
+
+    let address = matches.value_of("address").unwrap();
+    println!("Overwriting {} rom combinators from map tag '{}' on {}", rom.combinator_count(), tag, address);
+    if !matches.is_present("yes") {
+        print!("Continue? [y/N] ");
+        io::stdout().flush().expect("Could not write to stdout");
+        let mut answer = String::new();
+        io::stdin().read_line(&mut answer).expect("Could not read from stdin");
+        if !matches!(answer.trim(), "y" | "Y" | "yes") {
+            println!("Upload cancelled");
+            return;
+        }
+    }
+
+    let result = RconClient::connect(address, &password).and_then(|mut client| client.execute(&command));
+    match result {
+        Ok(response) => {
+            println!("{}", response.trim_end());
+            // Errors of the command, e.g. missing rom combinators, are part of the response.
+            if !response.starts_with(console::LOADED_PREFIX) {
+                process::exit(1);
+            }
+        }
+        Err(e) => {
+            eprintln!("Upload to {} failed: {}", address, e);
+            process::exit(1);
+        }
+    }
+}
+
+fn trace_diff_command(matches: &ArgMatches) {
+    let read = |name| trace::read_trace(matches.value_of(name).unwrap()).expect("Could not read trace file");
+    let divergence = match trace::diff(&read("left"), &read("right")) {
+        Some(divergence) => divergence,
+        None => {
+            println!("Traces are equal");
+            return;
+        }
+    };
+
+    let line = |line: Option<String>| line.unwrap_or_else(|| "<end of trace>".into());
+    println!("Traces diverge at line {}", divergence.line);
+    if let Some(common) = divergence.common {
+        println!("  both:  {}", common);
+    }
+    println!("  left:  {}", line(divergence.left));
+    println!("  right: {}", line(divergence.right));
+    process::exit(1);
+}
+
+fn simulate_command(matches: &ArgMatches) {
+    let ticks = if let Ok(ticks) = matches.value_of("ticks").unwrap().parse() {
+        ticks
+    } else {
+        eprintln!("Invalid argument(s). Try --help for more information.");
+        return;
+    };
+
+    let mut circuit = if let Some(circuit) = load_circuit(matches.value_of("input-file").unwrap()) {
+        circuit
+    } else {
+        return;
+    };
+    circuit.run(ticks);
+
+    println!("Outputs after {} ticks:", circuit.ticks);
+    for entity in circuit.combinators() {
+        let signals = circuit.output(entity).unwrap().iter().map(|(signal, value)| format!("{} = {}", signal, value)).collect::<Vec<_>>();
+        println!("{:>5}: {}", entity, signals.join(", "));
+    }
+}
+
+fn check_signals_command(matches: &ArgMatches) {
+    let ticks = if let Ok(ticks) = matches.value_of("ticks").unwrap().parse() {
+        ticks
+    } else {
+        eprintln!("Invalid argument(s). Try --help for more information.");
+        return;
+    };
+
+    let policy = match matches.value_of("policy") {
+        Some(file) => SignalPolicy::read(file).expect("Could not read signal policy file"),
+        None => SignalPolicy::default(),
+    };
+    let mut circuit = if let Some(circuit) = load_circuit(matches.value_of("input-file").unwrap()) {
+        circuit
+    } else {
+        return;
+    };
+
+    let violations = policy.check(&mut circuit, ticks);
+    if violations.is_empty() {
+        println!("No signal policy violations");
+        return;
+    }
+    for violation in &violations {
+        println!("{}", violation);
+    }
+    process::exit(1);
+}
+
+fn generate_command(matches: &ArgMatches) {
+    let blueprint = match matches.value_of("part").unwrap() {
+        "decoder" => generator::decoder_blueprint(),
+        "ram" => match generate_ram(matches) {
+            Ok(blueprint) => blueprint,
+            Err(e) => {
+                eprintln!("{}", e);
+                return;
+            }
+        },
+        part => unreachable!("Unknown part '{}'", part),
+    };
+
+    match matches.value_of("output-file") {
+        Some(output_file) => blueprint.write(output_file).expect("Could not create output file"),
+        None => println!("{}", blueprint.encode()),
+    }
+    if blueprint.entities.iter().any(|entity| entity.control_behavior.as_ref().is_some_and(|behavior| behavior.is_on == Some(false))) {
+        eprintln!("Switch on the constant combinator left of the memory bank after it is built to load the initial data");
+    }
+}
+
+fn generate_ram(matches: &ArgMatches) -> Result<Blueprint, String> {
+    let options = LinkOptions {
+        layout: matches.value_of("layout").map(|file| MemoryLayout::read(file).expect("Could not read memory layout file")),
+        ..LinkOptions::default()
+    };
+    let ram = options.layout.as_ref().and_then(|layout| layout.region_of_kind(RegionKind::Ram));
+    let (start, size) = match ram {
+        Some(ram) => (ram.start, ram.size),
+        None if options.layout.is_some() => return Err("Memory layout has no ram region".into()),
+        None => {
+            let start = matches.value_of("start").map_or(Some(PROGRAM_START), parse_address).ok_or_else(|| format!("Invalid start address '{}'", matches.value_of("start").unwrap()))?;
+            let size = matches.value_of("size").ok_or("Size of the memory bank is missing")?;
+            let size = parse_address(size).ok_or_else(|| format!("Invalid size '{}'", size))?;
+            (start, size)
+        }
+    };
+
+    let program = matches.value_of("data").map(|file| load_program(file, PROGRAM_START, &options));
+    let (data_start, data) = match &program {
+        Some(program) if ram.is_some() => (program.data_start, program.data.as_slice()),
+        Some(program) => (program.start, program.binary.as_slice()),
+        None => (start, &[][..]),
+    };
+    generator::ram_blueprint(start, size, data_start, data)
+}
+
+fn load_circuit(blueprint_file: &str) -> Option<Circuit> {
+    let blueprint = Blueprint::read(blueprint_file).expect("Could not read blueprint file");
+    match Circuit::new(&blueprint) {
+        Ok(circuit) => Some(circuit),
+        Err(e) => {
+            eprintln!("{}", e);
+            None
+        }
+    }
+}
+
+fn assemble_command(matches: &ArgMatches) {
+    let args = if let Some(file) = parse_arguments(matches) {
         file
     } else {
         eprintln!("Invalid argument(s). Try --help for more information.");
         return;
     };
 
-    let assembled = assemble(&args.input_file);
-    fs::write(args.output_file, &assembled).expect("Could not create output file");
+    let (archive_files, input_files): (Vec<_>, Vec<_>) = args.input_files.iter().partition(|file| has_extension(file, ARCHIVE_EXTENSION));
+    let archives = archive_files.iter().map(|archive_file| {
+        Archive::read(archive_file).expect("Could not read archive file")
+    }).collect::<Vec<_>>();
+    let mut objects = input_files.iter().map(|input_file| {
+        if has_extension(input_file, OBJECT_EXTENSION) {
+            Object::read(input_file).expect("Could not read object file")
+        } else {
+            assemble_object(input_file)
+        }
+    }).collect::<Vec<_>>();
+
+    if args.object_only {
+        objects[0].write(&args.output_file).expect("Could not create output file");
+        return;
+    }
+
+    if args.archive {
+        let members = objects.into_iter().chain(archives.into_iter().flat_map(|archive| archive.members)).collect();
+        Archive { members }.write(&args.output_file).expect("Could not create output file");
+        return;
+    }
+
+    objects.extend(archive::select_members(&objects, &archives));
+    let program = link(&objects, &args.link_options);
+    match &args.format {
+        OutputFormat::Binary => fs::write(&args.output_file, &program.binary),
+        OutputFormat::Lua(anchor) => {
+            let rom = RomPlan::new(&program.binary, program.start, args.rom_slots);
+            fs::write(&args.output_file, console::rom_loader(&rom, anchor) + "\n")
+        }
+        OutputFormat::Blueprint => {
+            let rom = RomPlan::new(&program.binary, program.start, args.rom_slots);
+            println!("Rom of {} words in {} constant combinators", program.binary.len() / 4, rom.combinator_count());
+            println!("Selector: {}", rom.selector());
+            rom.blueprint().write(&args.output_file)
+        }
+    }
+    .expect("Could not create output file");
+
+    match args.data_file {
+        Some(data_file) => fs::write(data_file, &program.data).expect("Could not create data output file"),
+        None if !program.data.is_empty() => {
+            eprintln!("[Warning] Initial ram content of data sections is not written. Use --data-output to write it.");
+        }
+        None => {}
+    }
+
+    if let Some(debug_info_file) = args.debug_info_file {
+        program.debug_info.write(debug_info_file).expect("Could not create debug info file");
+    }
+
+    if let Some(timing) = args.report {
+        println!("{}", Report::new(&program, &timing, args.rom_slots));
+    }
 }
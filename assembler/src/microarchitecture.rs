@@ -0,0 +1,113 @@
+use std::collections::HashMap;
+
+use crate::{
+    assembler::{AssemblyTranslation, InstructionSignature},
+    circuit::Signals,
+    ir::{IRCommand, IRParamType},
+};
+
+/// Internal signals the CPU decodes from the first word of an instruction, as named
+/// in MicroArchitecture.md.
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DecodedSignals {
+    /// I: Instruction type, `[IP] & 0xFF`.
+    pub i: i32,
+    /// V: `[IP] >> 8` (sign retaining), the location of jumps.
+    pub v: i32,
+    /// A: First register argument (2nd byte), 0 if the instruction has none.
+    pub a: i32,
+    /// B: Second register argument or byte immediate (3rd byte), 0 if the instruction has none.
+    pub b: i32,
+    /// X: Register the result is written to, 0 if the instruction writes none of its arguments.
+    pub x: i32,
+}
+
+impl DecodedSignals {
+    /// Signals as they are present on the wires, e.g. `signal-I`. Signals with value 0 are left out.
+    pub fn signals(&self) -> Signals {
+        [("signal-I", self.i), ("signal-V", self.v), ("signal-A", self.a), ("signal-B", self.b), ("signal-X", self.x)]
+            .into_iter()
+            .filter(|&(_, value)| value != 0)
+            .map(|(signal, value)| (signal.to_string(), value))
+            .collect()
+    }
+}
+
+/// Reference model of the instruction decoder for every instruction type the assembler produces.
+pub struct DecodeModel {
+    table: HashMap<u8, (InstructionSignature, bool)>,
+}
+
+impl DecodeModel {
+    pub fn new() -> DecodeModel {
+        DecodeModel {
+            table: AssemblyTranslation::new().decoding_table(),
+        }
+    }
+
+    /// All instruction types, ascending.
+    pub fn opcodes(&self) -> Vec<u8> {
+        let mut opcodes = self.table.keys().copied().collect::<Vec<_>>();
+        opcodes.sort_unstable();
+        opcodes
+    }
+
+    /// Decodes the first word of an instruction. Returns `None` for unknown instruction types.
+    pub fn decode(&self, word: u32) -> Option<DecodedSignals> {
+        let bytes = word.to_be_bytes();
+        let ((command, param1, param2), byte_immediates) = self.table.get(&bytes[3])?;
+
+        // Register arguments and byte immediates take the bytes left of the type, from right to left.
+        let arguments = [param1, param2].into_iter().filter(|&param| {
+            matches!(param, IRParamType::Register | IRParamType::MemoryAtRegister) || (*param == IRParamType::Immediate && *byte_immediates)
+        });
+        let mut registers = [0; 2];
+        for (index, _) in arguments.enumerate() {
+            registers[index] = bytes[2 - index] as i32;
+        }
+
+        Some(DecodedSignals {
+            i: bytes[3] as i32,
+            v: word as i32 >> 8,
+            a: registers[0],
+            b: registers[1],
+            x: if *param1 == IRParamType::Register && writes_first_argument(command) { registers[0] } else { 0 },
+        })
+    }
+
+    /// Number of words of the instruction, including the additional values for immediates.
+    pub fn size(&self, opcode: u8) -> Option<u32> {
+        let ((_, param1, param2), byte_immediates) = self.table.get(&opcode)?;
+        let words = [param1, param2]
+            .into_iter()
+            .filter(|&param| matches!(param, IRParamType::MemoryAtImmediate) || (*param == IRParamType::Immediate && !byte_immediates))
+            .count() as u32;
+        Some(1 + words)
+    }
+}
+
+impl Default for DecodeModel {
+    fn default() -> DecodeModel {
+        DecodeModel::new()
+    }
+}
+
+fn writes_first_argument(command: &IRCommand) -> bool {
+    !matches!(
+        command,
+        IRCommand::Cmp
+            | IRCommand::Jmp
+            | IRCommand::Jz
+            | IRCommand::Jnz
+            | IRCommand::Js
+            | IRCommand::Jns
+            | IRCommand::Jle
+            | IRCommand::Jgt
+            | IRCommand::Push
+            | IRCommand::Call
+            | IRCommand::Int
+            | IRCommand::Ret
+            | IRCommand::Halt
+            | IRCommand::Nop
+    )
+}
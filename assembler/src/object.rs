@@ -0,0 +1,165 @@
+use std::{fs, io, path::Path};
+
+use serde::{Deserialize, Serialize};
+
+use crate::debug_info::LineInfo;
+
+/// File extension of object files.
+pub const OBJECT_EXTENSION: &str = "o";
+
+/// Section that instructions are placed in if no `.section` directive is used.
+pub const DEFAULT_SECTION: &str = ".text";
+
+/// A single assembled source file whose references to labels outside of the
+/// file (or to absolute label addresses) are not resolved yet.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Object {
+    /// Name of the source file the object was assembled from.
+    pub source: String,
+    pub sections: Vec<Section>,
+    /// Labels which are expected to be defined by another object.
+    pub externs: Vec<String>,
+}
+
+/// Continuous block of words which is placed by the linker as a whole.
+///
+/// All offsets are in words and relative to the start of the section.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Section {
+    pub name: String,
+    pub kind: SectionKind,
+    pub words: Vec<u32>,
+    pub symbols: Vec<ObjectSymbol>,
+    pub relocations: Vec<Relocation>,
+    /// Source lines of all instructions. `LineInfo::file` is always 0.
+    pub lines: Vec<LineInfo>,
+    pub memory_operands: Vec<MemoryOperand>,
+    /// Whether the last line of the section is HALT.
+    pub ends_with_halt: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SectionKind {
+    /// Instructions, placed in rom.
+    Text,
+    /// Initialized variables, placed in ram.
+    Data,
+    /// Variables initialized with 0. Only reserves addresses in ram, no words are emitted.
+    Bss,
+}
+
+/// Absolute memory address used by an instruction, e.g. `MOV A, [5]`.
+/// The linker checks these against the memory layout.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct MemoryOperand {
+    pub address: i32,
+    pub line: usize,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ObjectSymbol {
+    pub name: String,
+    pub offset: u32,
+    /// Global symbols may be referenced by other objects.
+    pub global: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Relocation {
+    /// Word which has to be patched.
+    pub offset: u32,
+    pub kind: RelocationKind,
+    pub symbol: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum RelocationKind {
+    /// i24 location of a jump or call, relative to the patched word.
+    Relative,
+    /// Absolute address of the symbol, written to the whole word.
+    Absolute,
+}
+
+impl Object {
+    pub fn read<P: AsRef<Path>>(path: P) -> io::Result<Object> {
+        let content = fs::read_to_string(path)?;
+        serde_json::from_str(&content).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+
+    pub fn write<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let content = serde_json::to_string(self).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        fs::write(path, content)
+    }
+
+    /// Finds a label of the object and returns it with the index of its section.
+    pub fn symbol(&self, name: &str) -> Option<(usize, &ObjectSymbol)> {
+        self.sections
+            .iter()
+            .enumerate()
+            .find_map(|(index, section)| Some((index, section.symbols.iter().find(|symbol| symbol.name == name)?)))
+    }
+
+    pub fn global_symbols(&self) -> impl Iterator<Item = &ObjectSymbol> {
+        self.sections.iter().flat_map(|section| &section.symbols).filter(|symbol| symbol.global)
+    }
+
+    /// Symbols which are referenced by the object, but not defined in it.
+    pub fn undefined_symbols(&self) -> impl Iterator<Item = &str> {
+        self.sections
+            .iter()
+            .flat_map(|section| &section.relocations)
+            .map(|relocation| relocation.symbol.as_str())
+            .filter(|&symbol| self.symbol(symbol).is_none())
+    }
+}
+
+impl Section {
+    pub fn new(name: &str, kind: SectionKind) -> Section {
+        Section {
+            name: name.into(),
+            kind,
+            words: Vec::new(),
+            symbols: Vec::new(),
+            relocations: Vec::new(),
+            lines: Vec::new(),
+            memory_operands: Vec::new(),
+            ends_with_halt: false,
+        }
+    }
+
+    pub fn size(&self) -> u32 {
+        self.words.len() as u32
+    }
+}
+
+impl SectionKind {
+    /// Sections named `.data` or `.bss` (or starting with `.data.` or `.bss.`)
+    /// are data and bss sections, all other sections contain instructions.
+    pub fn from_name(name: &str) -> SectionKind {
+        let matches = |prefix: &str| name == prefix || name.starts_with(&format!("{}.", prefix));
+        if matches(".data") {
+            SectionKind::Data
+        } else if matches(".bss") {
+            SectionKind::Bss
+        } else {
+            SectionKind::Text
+        }
+    }
+}
+
+impl RelocationKind {
+    /// Patches `word` (located at `address`) so that it references `target`.
+    /// Returns `None` if the target is out of range for a relative location.
+    pub fn patch(&self, word: u32, address: u32, target: u32) -> Option<u32> {
+        match self {
+            RelocationKind::Relative => {
+                let location = target as i64 - address as i64;
+                if !(-(1 << 23)..(1 << 23)).contains(&location) {
+                    return None;
+                }
+                Some(((location as u32) << 8) | (word & 0xff))
+            }
+            RelocationKind::Absolute => Some(target),
+        }
+    }
+}
@@ -0,0 +1,120 @@
+use std::{cmp::Reverse, collections::HashMap, fmt::Write};
+
+use crate::{
+    debug_info::DebugInfo,
+    emulator::{Emulator, Step},
+    ir::{IRCommand, IRRegister},
+};
+
+/// Executed instructions and their ticks.
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cost {
+    pub instructions: u64,
+    pub ticks: u64,
+}
+
+impl Cost {
+    fn add(&mut self, step: &Step) {
+        self.instructions += 1;
+        self.ticks += step.ticks;
+    }
+}
+
+/// Attributes executed instructions to source lines and functions.
+///
+/// Functions are identified by the target address of CALL and INT, the program
+/// start is the outermost function. The costs of a function only include its own
+/// instructions, not those of the functions it calls.
+pub struct Profiler {
+    debug_info: DebugInfo,
+    /// Start addresses of the called functions, innermost last.
+    stack: Vec<i32>,
+    lines: HashMap<Option<(usize, usize)>, Cost>,
+    functions: HashMap<i32, Cost>,
+    stacks: HashMap<Vec<i32>, u64>,
+}
+
+impl Profiler {
+    /// Starts profiling a program which is executed from `start`.
+    pub fn new(debug_info: DebugInfo, start: i32) -> Profiler {
+        Profiler {
+            debug_info,
+            stack: vec![start],
+            lines: HashMap::new(),
+            functions: HashMap::new(),
+            stacks: HashMap::new(),
+        }
+    }
+
+    /// Adds `step`, which was just executed by `emulator`.
+    pub fn record(&mut self, emulator: &Emulator, step: &Step) {
+        let line = self.debug_info.line_at(step.address as u32).map(|line| (line.file, line.line));
+        self.lines.entry(line).or_default().add(step);
+        self.functions.entry(*self.stack.last().unwrap()).or_default().add(step);
+        *self.stacks.entry(self.stack.clone()).or_default() += step.ticks;
+
+        match step.instruction.command {
+            IRCommand::Call | IRCommand::Int => self.stack.push(emulator.register(IRRegister::IP)),
+            // The outermost function never returns.
+            IRCommand::Ret if self.stack.len() > 1 => {
+                self.stack.pop();
+            }
+            _ => {}
+        }
+    }
+
+    /// Costs per function, most ticks first.
+    pub fn functions(&self) -> Vec<(String, Cost)> {
+        let mut functions = self.functions.iter().map(|(&address, &cost)| (self.function_name(address), cost)).collect::<Vec<_>>();
+        functions.sort_by(|(name, cost), (other_name, other_cost)| (Reverse(cost.ticks), name).cmp(&(Reverse(other_cost.ticks), other_name)));
+        functions
+    }
+
+    /// Costs per source line (`file:line`), most ticks first.
+    pub fn lines(&self) -> Vec<(String, Cost)> {
+        let location = |line: &Option<(usize, usize)>| match line {
+            Some((file, line)) => format!("{}:{}", self.debug_info.files.get(*file).map_or("?", String::as_str), line),
+            None => "<unknown>".into(),
+        };
+        let mut lines = self.lines.iter().collect::<Vec<_>>();
+        lines.sort_by_key(|&(line, cost)| (Reverse(cost.ticks), *line));
+        lines.into_iter().map(|(line, &cost)| (location(line), cost)).collect()
+    }
+
+    /// Sorted text report of the functions and lines.
+    pub fn report(&self) -> String {
+        let total = self.functions.values().map(|cost| cost.ticks).sum::<u64>().max(1);
+        let mut output = String::new();
+        for (title, costs) in [("Function", self.functions()), ("Line", self.lines())] {
+            writeln!(output, "{:>10} {:>7} {:>12}  {}", "Ticks", "%", "Instructions", title).unwrap();
+            for (name, cost) in costs {
+                let percent = 100.0 * cost.ticks as f64 / total as f64;
+                writeln!(output, "{:>10} {:>6.2}% {:>12}  {}", cost.ticks, percent, cost.instructions, name).unwrap();
+            }
+            writeln!(output).unwrap();
+        }
+        output.trim_end().into()
+    }
+
+    /// Ticks per call stack in the folded format of flamegraph tools,
+    /// e.g. `start;loop;add 42`. Sorted by the stack.
+    pub fn folded_stacks(&self) -> String {
+        let mut lines = self
+            .stacks
+            .iter()
+            .map(|(stack, ticks)| {
+                let names = stack.iter().map(|&address| self.function_name(address)).collect::<Vec<_>>();
+                format!("{} {}", names.join(";"), ticks)
+            })
+            .collect::<Vec<_>>();
+        lines.sort();
+        lines.join("\n")
+    }
+
+    fn function_name(&self, address: i32) -> String {
+        match self.debug_info.symbols.iter().find(|symbol| symbol.address as i32 == address) {
+            Some(symbol) => symbol.name.clone(),
+            None => format!("0x{:04x}", address),
+        }
+    }
+}
@@ -0,0 +1,128 @@
+use std::{
+    io::{self, ErrorKind, Read, Write},
+    net::{TcpStream, ToSocketAddrs},
+    time::Duration,
+};
+
+pub const SERVERDATA_AUTH: i32 = 3;
+pub const SERVERDATA_AUTH_RESPONSE: i32 = 2;
+pub const SERVERDATA_EXECCOMMAND: i32 = 2;
+pub const SERVERDATA_RESPONSE_VALUE: i32 = 0;
+
+/// Id and type of a packet and the two null bytes terminating the body and the packet.
+const MIN_PACKET_SIZE: usize = 10;
+/// Largest packet accepted from the server. Factorio is not limited to the
+/// 4096 bytes of other servers, so long responses may come in a single packet.
+const MAX_PACKET_SIZE: usize = 4 * 1024 * 1024;
+
+/// Time to wait for a response before giving up.
+const TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Packet of the Source RCON protocol, which Factorio implements.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Packet {
+    pub id: i32,
+    pub kind: i32,
+    pub body: String,
+}
+
+impl Packet {
+    /// Reads a packet: its size, id and type as little-endian 32 bit integers, followed by the
+    /// null terminated body and another null byte. The size counts all bytes after itself.
+    pub fn read<R: Read>(reader: &mut R) -> io::Result<Packet> {
+        let mut word = [0; 4];
+        reader.read_exact(&mut word)?;
+        let size = i32::from_le_bytes(word);
+        if !(MIN_PACKET_SIZE as i32..=MAX_PACKET_SIZE as i32).contains(&size) {
+            return Err(io::Error::new(ErrorKind::InvalidData, format!("Invalid RCON packet size {}", size)));
+        }
+
+        let mut content = vec![0; size as usize];
+        reader.read_exact(&mut content)?;
+        let body = match content.split_off(8).as_slice() {
+            [body @ .., 0, 0] => String::from_utf8(body.to_vec()).map_err(|e| io::Error::new(ErrorKind::InvalidData, e))?,
+            _ => return Err(io::Error::new(ErrorKind::InvalidData, "RCON packet is not null terminated")),
+        };
+        Ok(Packet {
+            id: i32::from_le_bytes(content[..4].try_into().unwrap()),
+            kind: i32::from_le_bytes(content[4..].try_into().unwrap()),
+            body,
+        })
+    }
+
+    pub fn write<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        let size = MIN_PACKET_SIZE + self.body.len();
+        let mut bytes = Vec::with_capacity(size + 4);
+        bytes.extend((size as i32).to_le_bytes());
+        bytes.extend(self.id.to_le_bytes());
+        bytes.extend(self.kind.to_le_bytes());
+        bytes.extend(self.body.as_bytes());
+        bytes.extend([0, 0]);
+        writer.write_all(&bytes)?;
+        writer.flush()
+    }
+}
+
+/// Client for the RCON interface of a Factorio server, e.g. one started with
+/// `--rcon-port 27015 --rcon-password secret`.
+pub struct RconClient<S> {
+    stream: S,
+    next_id: i32,
+}
+
+impl RconClient<TcpStream> {
+    pub fn connect<A: ToSocketAddrs>(address: A, password: &str) -> io::Result<RconClient<TcpStream>> {
+        let stream = TcpStream::connect(address)?;
+        stream.set_nodelay(true)?;
+        stream.set_read_timeout(Some(TIMEOUT))?;
+        RconClient::new(stream, password)
+    }
+}
+
+impl<S: Read + Write> RconClient<S> {
+    /// Authenticates on the stream with the password.
+    pub fn new(stream: S, password: &str) -> io::Result<RconClient<S>> {
+        let mut client = RconClient { stream, next_id: 1 };
+        let id = client.send(SERVERDATA_AUTH, password)?;
+        loop {
+            // Servers may send an empty response value before the auth response.
+            let packet = Packet::read(&mut client.stream)?;
+            if packet.kind != SERVERDATA_AUTH_RESPONSE {
+                continue;
+            }
+            if packet.id == -1 {
+                return Err(io::Error::new(ErrorKind::PermissionDenied, "RCON authentication failed, wrong password"));
+            }
+            if packet.id == id {
+                return Ok(client);
+            }
+        }
+    }
+
+    /// Executes a console command and returns the output of the server.
+    pub fn execute(&mut self, command: &str) -> io::Result<String> {
+        let id = self.send(SERVERDATA_EXECCOMMAND, command)?;
+        // Long responses may be split into several packets. Commands are answered
+        // in order, so the response to an empty command marks the end.
+        let end = self.send(SERVERDATA_EXECCOMMAND, "")?;
+
+        let mut response = String::new();
+        loop {
+            let packet = Packet::read(&mut self.stream)?;
+            if packet.id == end {
+                return Ok(response);
+            }
+            // Packets with other ids are left over from earlier commands.
+            if packet.id == id {
+                response.push_str(&packet.body);
+            }
+        }
+    }
+
+    fn send(&mut self, kind: i32, body: &str) -> io::Result<i32> {
+        let id = self.next_id;
+        self.next_id = self.next_id.checked_add(1).unwrap_or(1);
+        Packet { id, kind, body: body.into() }.write(&mut self.stream)?;
+        Ok(id)
+    }
+}
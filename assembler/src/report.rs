@@ -0,0 +1,174 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt,
+};
+
+use crate::{
+    disassembler::{Decoder, Instruction, Operand},
+    ir::IRCommand,
+    rom::RomPlan,
+    timing::TimingModel,
+    Program,
+};
+
+/// Static summary of the footprint of a linked program, without executing it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Report {
+    /// Words of the rom.
+    pub words: u32,
+    /// Words of the initial ram content.
+    pub data_words: u32,
+    /// Words from every symbol up to the next one, in address order. Words in front of
+    /// the first symbol (e.g. the startup code of the linker) are named after their address.
+    pub symbols: Vec<(String, u32)>,
+    /// Number of instructions per opcode, ascending by opcode.
+    pub opcodes: Vec<(u8, String, u32)>,
+    /// Words per constant combinator of the rom.
+    pub rom_slots: usize,
+    pub rom_combinators: usize,
+    pub blocks: Vec<Block>,
+}
+
+/// Straight-line block of instructions: it is only entered at its start, at a symbol or jump
+/// target, and ends with the last instruction before the next entry or with a jump, call, INT,
+/// RET or HALT.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Block {
+    pub start: u32,
+    /// One past the last word of the block.
+    pub end: u32,
+    pub instructions: u32,
+    /// Ticks to execute every instruction of the block once.
+    pub ticks: u64,
+}
+
+impl Report {
+    pub fn new(program: &Program, timing: &TimingModel, rom_slots: usize) -> Report {
+        let words = program.binary.chunks_exact(4).map(|word| u32::from_be_bytes(word.try_into().unwrap())).collect::<Vec<_>>();
+        let data_words = program.data.len() as u32 / 4;
+        let rom = program.start..program.start + words.len() as u32;
+        let ram = program.data_start..program.data_start + data_words;
+
+        // Every symbol covers the words up to the next symbol or the end of its region.
+        let mut starts = program.debug_info.symbols.iter().map(|symbol| (symbol.address, symbol.name.clone())).collect::<Vec<_>>();
+        if !rom.is_empty() && !starts.iter().any(|&(address, _)| address == rom.start) {
+            starts.push((rom.start, format!("0x{:04x}", rom.start)));
+        }
+        starts.sort();
+        let symbols = starts
+            .iter()
+            .enumerate()
+            .map(|(index, (address, name))| {
+                let region_end = if ram.contains(address) { ram.end } else { rom.end };
+                let end = starts.get(index + 1).map_or(region_end, |&(next, _)| next.min(region_end));
+                (name.clone(), end.saturating_sub(*address))
+            })
+            .collect();
+
+        // Decode the instructions of the rom, skipping the words of .word and .space.
+        let decoder = Decoder::new();
+        let mut instructions = Vec::new();
+        let mut address = rom.start;
+        while address < rom.end {
+            let data = program.debug_info.line_at(address).filter(|line| line.data);
+            if let Some(line) = data {
+                address = line.end;
+                continue;
+            }
+            let instruction = decoder.decode(&words[(address - rom.start) as usize..]);
+            let size = instruction.as_ref().map_or(1, |instruction| instruction.size);
+            instructions.push((address, instruction));
+            address += size;
+        }
+
+        let mut opcodes = BTreeMap::new();
+        for instruction in instructions.iter().filter_map(|(_, instruction)| instruction.as_ref()) {
+            opcodes.entry(instruction.opcode).or_insert_with(|| (signature(instruction), 0)).1 += 1;
+        }
+
+        let mut entries = program.debug_info.symbols.iter().map(|symbol| symbol.address).collect::<BTreeSet<_>>();
+        entries.extend(instructions.iter().filter_map(|(address, instruction)| instruction.as_ref()?.target(*address)));
+        let mut blocks: Vec<Block> = Vec::new();
+        let mut open = false;
+        for (address, instruction) in &instructions {
+            let instruction = match instruction {
+                Some(instruction) => instruction,
+                None => {
+                    open = false;
+                    continue;
+                }
+            };
+            let contiguous = blocks.last().is_some_and(|block| block.end == *address);
+            if !open || !contiguous || entries.contains(address) {
+                blocks.push(Block {
+                    start: *address,
+                    end: *address,
+                    instructions: 0,
+                    ticks: 0,
+                });
+            }
+            let block = blocks.last_mut().unwrap();
+            block.end = address + instruction.size;
+            block.instructions += 1;
+            block.ticks += timing.ticks(instruction);
+            open = !ends_block(&instruction.command);
+        }
+
+        Report {
+            words: words.len() as u32,
+            data_words,
+            symbols,
+            opcodes: opcodes.into_iter().map(|(opcode, (signature, count))| (opcode, signature, count)).collect(),
+            rom_slots,
+            rom_combinators: RomPlan::new(&program.binary, program.start, rom_slots).combinator_count(),
+            blocks,
+        }
+    }
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Words: {} rom, {} ram", self.words, self.data_words)?;
+        writeln!(f, "Rom constant combinators: {} ({} words per combinator)", self.rom_combinators, self.rom_slots)?;
+
+        writeln!(f, "\n{:>10}  Symbol", "Words")?;
+        for (name, words) in &self.symbols {
+            writeln!(f, "{:>10}  {}", words, name)?;
+        }
+
+        writeln!(f, "\n{:>10}  Opcode", "Count")?;
+        for (opcode, signature, count) in &self.opcodes {
+            writeln!(f, "{:>10}  0x{:02x} {}", count, opcode, signature)?;
+        }
+
+        write!(f, "\n{:>10} {:>12}  Block", "Ticks", "Instructions")?;
+        for block in &self.blocks {
+            write!(f, "\n{:>10} {:>12}  {:04x}..{:04x}", block.ticks, block.instructions, block.start, block.end)?;
+        }
+        Ok(())
+    }
+}
+
+/// Mnemonic and parameter types of the instruction, e.g. `MOV reg, [imm]`.
+fn signature(instruction: &Instruction) -> String {
+    let operands = instruction
+        .operands
+        .iter()
+        .map(|operand| match operand {
+            Operand::Register(_) => "reg",
+            Operand::Immediate(_) => "imm",
+            Operand::MemoryAtRegister(_) => "[reg]",
+            Operand::MemoryAtImmediate(_) => "[imm]",
+            Operand::Location(_) => "label",
+        })
+        .collect::<Vec<_>>();
+    if operands.is_empty() {
+        instruction.command.mnemonic().into()
+    } else {
+        format!("{} {}", instruction.command.mnemonic(), operands.join(", "))
+    }
+}
+
+fn ends_block(command: &IRCommand) -> bool {
+    command.is_relative_jump() || matches!(command, IRCommand::Int | IRCommand::Ret | IRCommand::Halt)
+}
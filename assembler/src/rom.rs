@@ -0,0 +1,189 @@
+use std::{cmp::Ordering, fmt};
+
+use crate::blueprint::{Blueprint, ControlBehavior, Entity, Filter, Position, SignalId};
+
+/// Slots of a constant combinator.
+pub const MAX_SLOTS: usize = 20;
+
+/// Signal of the word if every combinator holds a single word, like the console
+/// loader writes it: the memory value itself.
+const SINGLE_SLOT_SIGNAL: &str = "signal-green";
+
+/// Signals of the slots if the words are packed, in slot order. None of them is used by a wire of the
+/// CPU (see MicroArchitecture.md and `SignalPolicy::default`), so the ROM can not disturb the buses.
+const PACKED_SIGNALS: [&str; MAX_SLOTS] = [
+    "signal-5", "signal-6", "signal-7", "signal-8", "signal-9", "signal-D", "signal-E", "signal-F", "signal-G", "signal-H",
+    "signal-J", "signal-K", "signal-L", "signal-M", "signal-O", "signal-Q", "signal-T", "signal-U", "signal-Y", "signal-blue",
+];
+
+/// Assignment of the words of a program to the slots of the ROM's constant combinators.
+/// The word at `start + n` is in combinator `n / slots`, on the signal of slot `n % slots`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RomPlan {
+    pub start: u32,
+    pub slots: usize,
+    /// Words of every combinator, the last one may be shorter.
+    pub combinators: Vec<Vec<i32>>,
+}
+
+/// Parameters of the logic which reads a word from the ROM: an arithmetic combinator computes the
+/// row `(address - start) / slots` and a decider per combinator passes its signals if the row is
+/// its index. Another arithmetic combinator computes the slot `(address - start) % slots`, which
+/// selects the signal that is converted to Green.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Selector {
+    pub start: u32,
+    pub slots: usize,
+    pub rows: usize,
+    pub signals: Vec<&'static str>,
+}
+
+impl fmt::Display for Selector {
+    /// Parameters for building the read logic, e.g. `row = (address - 1) / 3 of 4 rows, slot = (address - 1) % 3 on signal-5, signal-6, signal-7`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "row = (address - {start}) / {slots} of {} rows, slot = (address - {start}) % {slots} on {}",
+            self.rows,
+            self.signals.join(", "),
+            start = self.start,
+            slots = self.slots,
+        )
+    }
+}
+
+/// Position of a word in the ROM.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Location {
+    pub combinator: usize,
+    pub slot: usize,
+    pub signal: &'static str,
+}
+
+impl RomPlan {
+    /// Packs the binary with `slots` words per combinator. Panics if `slots` is not between 1 and `MAX_SLOTS`.
+    pub fn new(binary: &[u8], start: u32, slots: usize) -> RomPlan {
+        assert!((1..=MAX_SLOTS).contains(&slots), "A constant combinator has 1 to {} slots", MAX_SLOTS);
+        let words = binary.chunks_exact(4).map(|word| i32::from_be_bytes(word.try_into().unwrap())).collect::<Vec<_>>();
+        RomPlan {
+            start,
+            slots,
+            combinators: words.chunks(slots).map(<[i32]>::to_vec).collect(),
+        }
+    }
+
+    /// Reads the words from the constant combinators of a blueprint exported with the same number of
+    /// slots. Combinators are ordered by address like the console loader does: by x, then by y.
+    /// Words not present in a combinator are 0, trailing zeros are removed.
+    pub fn unpack(blueprint: &Blueprint, start: u32, slots: usize) -> Result<RomPlan, String> {
+        if !(1..=MAX_SLOTS).contains(&slots) {
+            return Err(format!("A constant combinator has 1 to {} slots", MAX_SLOTS));
+        }
+        let signals = slot_signals(slots);
+        let mut entities = blueprint.entities.iter().filter(|entity| entity.name == "constant-combinator").collect::<Vec<_>>();
+        entities.sort_by(|a, b| compare_positions(&a.position, &b.position));
+
+        let mut combinators = Vec::new();
+        for entity in entities {
+            let mut words = vec![0i32; slots];
+            let filters = entity.control_behavior.as_ref().and_then(|behavior| behavior.filters.as_ref());
+            for filter in filters.into_iter().flatten() {
+                let slot = signals
+                    .iter()
+                    .position(|&signal| filter.signal.kind == "virtual" && filter.signal.name == signal)
+                    .ok_or_else(|| format!("Signal {} of entity {} is not a rom slot", filter.signal.name, entity.entity_number))?;
+                words[slot] = words[slot].wrapping_add(filter.count);
+            }
+            combinators.push(words);
+        }
+
+        // Remove trailing zeros and the combinators left empty.
+        while let Some(last) = combinators.last_mut() {
+            while last.last() == Some(&0) {
+                last.pop();
+            }
+            if !last.is_empty() {
+                break;
+            }
+            combinators.pop();
+        }
+        Ok(RomPlan { start, slots, combinators })
+    }
+
+    pub fn combinator_count(&self) -> usize {
+        self.combinators.len()
+    }
+
+    pub fn words(&self) -> Vec<i32> {
+        self.combinators.concat()
+    }
+
+    pub fn binary(&self) -> Vec<u8> {
+        self.words().into_iter().flat_map(i32::to_be_bytes).collect()
+    }
+
+    pub fn locate(&self, address: u32) -> Option<Location> {
+        let offset = address.checked_sub(self.start)? as usize;
+        let (combinator, slot) = (offset / self.slots, offset % self.slots);
+        self.combinators.get(combinator)?.get(slot)?;
+        Some(Location {
+            combinator,
+            slot,
+            signal: slot_signals(self.slots)[slot],
+        })
+    }
+
+    pub fn selector(&self) -> Selector {
+        Selector {
+            start: self.start,
+            slots: self.slots,
+            rows: self.combinators.len(),
+            signals: slot_signals(self.slots).to_vec(),
+        }
+    }
+
+    /// Constant combinators in a row from left to right, words with the value 0 leave their slot empty.
+    pub fn blueprint(&self) -> Blueprint {
+        let signals = slot_signals(self.slots);
+        let entities = self
+            .combinators
+            .iter()
+            .enumerate()
+            .map(|(index, words)| Entity {
+                entity_number: index as u32 + 1,
+                name: "constant-combinator".into(),
+                position: Position { x: index as f64 + 0.5, y: 0.5 },
+                direction: None,
+                control_behavior: Some(ControlBehavior {
+                    filters: Some(
+                        words
+                            .iter()
+                            .enumerate()
+                            .filter(|&(_, &word)| word != 0)
+                            .map(|(slot, &count)| Filter {
+                                signal: SignalId::virtual_signal(signals[slot]),
+                                count,
+                                index: slot as u32 + 1,
+                            })
+                            .collect(),
+                    ),
+                    ..ControlBehavior::default()
+                }),
+                connections: Default::default(),
+            })
+            .collect();
+        Blueprint::new("ROM", entities)
+    }
+}
+
+fn slot_signals(slots: usize) -> &'static [&'static str] {
+    if slots == 1 {
+        &[SINGLE_SLOT_SIGNAL]
+    } else {
+        &PACKED_SIGNALS[..slots]
+    }
+}
+
+fn compare_positions(a: &Position, b: &Position) -> Ordering {
+    a.x.total_cmp(&b.x).then(a.y.total_cmp(&b.y))
+}
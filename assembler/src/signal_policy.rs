@@ -0,0 +1,132 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt, fs, io,
+    path::Path,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::circuit::{Circuit, WireColor};
+
+/// Allocation of signals to the buses of a CPU build, read from a JSON file like:
+///
+/// ```json
+/// {
+///   "buses": [
+///     { "name": "memory", "signals": ["signal-green"] },
+///     { "name": "registers", "signals": ["signal-0", "signal-1"], "allowed": ["signal-red"] }
+///   ]
+/// }
+/// ```
+///
+/// A wire belongs to a bus if it carries one of the signals of the bus. Every other
+/// signal on the wire is a violation, unless it is explicitly allowed for that bus.
+/// Wires without any bus signal are internal and not checked.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct SignalPolicy {
+    pub buses: Vec<Bus>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Bus {
+    pub name: String,
+    /// Signals which identify wires of the bus.
+    pub signals: Vec<String>,
+    /// Additional signals which may be present on the bus.
+    #[serde(default)]
+    pub allowed: Vec<String>,
+}
+
+/// Signal on a wire of a bus it does not belong to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Violation {
+    pub bus: String,
+    pub signal: String,
+    pub color: WireColor,
+    /// Entities connected to the wire.
+    pub entities: Vec<u32>,
+    /// First tick the signal was present in the simulation, `None` if it
+    /// was only found by the static analysis of the possible signals.
+    pub tick: Option<u64>,
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let color = match self.color {
+            WireColor::Red => "red",
+            WireColor::Green => "green",
+        };
+        let entities = self.entities.iter().map(u32::to_string).collect::<Vec<_>>().join(", ");
+        write!(f, "{} on {} {} wire of entities {}", self.signal, self.bus, color, entities)?;
+        match self.tick {
+            Some(tick) => write!(f, " (present at tick {})", tick),
+            None => write!(f, " (possible)"),
+        }
+    }
+}
+
+impl Default for SignalPolicy {
+    /// Buses as described in MicroArchitecture.md. Internal signals like Pink and C
+    /// must not be present on memory or register wires.
+    fn default() -> SignalPolicy {
+        let signals = |names: &[&str]| names.iter().map(|name| format!("signal-{}", name)).collect();
+        SignalPolicy {
+            buses: vec![
+                Bus {
+                    name: "memory".into(),
+                    signals: signals(&["green"]),
+                    allowed: Vec::new(),
+                },
+                Bus {
+                    name: "registers".into(),
+                    signals: signals(&["yellow", "red", "0", "1", "2", "3", "4", "N", "I", "V", "P", "Z", "S", "A", "B", "X", "cyan"]),
+                    allowed: Vec::new(),
+                },
+            ],
+        }
+    }
+}
+
+impl SignalPolicy {
+    pub fn read<P: AsRef<Path>>(path: P) -> io::Result<SignalPolicy> {
+        let content = fs::read_to_string(path)?;
+        serde_json::from_str(&content).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+
+    /// Checks all wires of the circuit. The signals of a wire are those which may be present
+    /// according to the static analysis and those present while simulating `ticks` ticks.
+    pub fn check(&self, circuit: &mut Circuit, ticks: u64) -> Vec<Violation> {
+        let possible = circuit.possible_signals();
+        // First tick every signal was present on each network.
+        let mut first_seen = vec![BTreeMap::<String, u64>::new(); circuit.networks().len()];
+        for step in 0..=ticks {
+            for (network, seen) in first_seen.iter_mut().enumerate() {
+                for signal in circuit.values(network).keys() {
+                    seen.entry(signal.clone()).or_insert(circuit.ticks);
+                }
+            }
+            if step < ticks {
+                circuit.tick();
+            }
+        }
+
+        let mut violations = Vec::new();
+        for (index, network) in circuit.networks().iter().enumerate() {
+            let mut signals = possible[index].clone();
+            signals.extend(first_seen[index].keys().cloned());
+
+            for bus in self.buses.iter().filter(|bus| bus.signals.iter().any(|signal| signals.contains(signal))) {
+                for signal in signals.iter().filter(|&signal| !bus.signals.contains(signal) && !bus.allowed.contains(signal)) {
+                    violations.push(Violation {
+                        bus: bus.name.clone(),
+                        signal: signal.clone(),
+                        color: network.color,
+                        entities: network.points.iter().map(|&(entity, _)| entity).collect::<BTreeSet<_>>().into_iter().collect(),
+                        tick: first_seen[index].get(signal).copied(),
+                    });
+                }
+            }
+        }
+        violations
+    }
+}
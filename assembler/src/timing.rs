@@ -0,0 +1,87 @@
+use std::{collections::HashMap, fs, io, path::Path};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    disassembler::{Instruction, Operand},
+    ir::IRCommand,
+};
+
+/// Game ticks per second at `game.speed = 1`.
+pub const TICKS_PER_SECOND: f64 = 60.0;
+
+/// Number of game ticks instructions take in-game, read from a JSON file like:
+///
+/// ```json
+/// {
+///   "base": 3,
+///   "per_extra_word": 1,
+///   "per_memory_access": 2,
+///   "instructions": { "DIV": 4, "0x05": 5 }
+/// }
+/// ```
+///
+/// The clock counter C steps through every instruction, so each instruction takes
+/// `base` ticks (or the ticks given in `instructions` for its opcode or mnemonic)
+/// plus the ticks for loading its additional words and accessing memory.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct TimingModel {
+    pub base: u64,
+    /// Ticks for every word of an instruction after the first one.
+    pub per_extra_word: u64,
+    /// Ticks for every memory operand and stack access.
+    pub per_memory_access: u64,
+    /// Ticks replacing `base` for specific instructions. Keys are opcodes
+    /// like `0x13` or mnemonics like `DIV`. Opcodes take precedence.
+    #[serde(default)]
+    pub instructions: HashMap<String, u64>,
+}
+
+impl Default for TimingModel {
+    fn default() -> TimingModel {
+        TimingModel {
+            base: 3,
+            per_extra_word: 1,
+            per_memory_access: 2,
+            instructions: HashMap::new(),
+        }
+    }
+}
+
+impl TimingModel {
+    pub fn read<P: AsRef<Path>>(path: P) -> io::Result<TimingModel> {
+        let content = fs::read_to_string(path)?;
+        serde_json::from_str(&content).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+
+    /// Ticks the instruction takes to execute.
+    pub fn ticks(&self, instruction: &Instruction) -> u64 {
+        let base = self
+            .instructions
+            .get(&format!("0x{:02x}", instruction.opcode))
+            .or_else(|| self.instructions.get(instruction.command.mnemonic()))
+            .copied()
+            .unwrap_or(self.base);
+
+        base + (instruction.size as u64 - 1) * self.per_extra_word + memory_accesses(instruction) * self.per_memory_access
+    }
+}
+
+/// Number of memory reads and writes of the instruction.
+fn memory_accesses(instruction: &Instruction) -> u64 {
+    let operands = instruction
+        .operands
+        .iter()
+        .filter(|operand| matches!(operand, Operand::MemoryAtRegister(_) | Operand::MemoryAtImmediate(_)))
+        .count() as u64;
+    let stack = match instruction.command {
+        IRCommand::Push | IRCommand::Pop | IRCommand::Call | IRCommand::Int | IRCommand::Ret => 1,
+        _ => 0,
+    };
+    operands + stack
+}
+
+/// Real time in seconds the given number of ticks take at `game_speed`.
+pub fn seconds(ticks: u64, game_speed: f64) -> f64 {
+    ticks as f64 / (TICKS_PER_SECOND * game_speed)
+}
@@ -0,0 +1,93 @@
+use std::{
+    fs::File,
+    io::{self, BufRead, BufReader, Write},
+    path::Path,
+};
+
+use crate::{
+    emulator::{Emulator, Flags, Step},
+    ir::IRRegister,
+};
+
+/// Records executed instructions, one line per instruction:
+///
+/// ```text
+/// 0007: CALL +6 ; SP=255 [0x0100]=7
+/// ```
+///
+/// The address and instruction are followed by the registers and flags
+/// which changed and the memory and device writes of the instruction.
+/// IP is left out, it is the address on the next line.
+pub struct Tracer<W: Write> {
+    writer: W,
+    registers: [i32; IRRegister::ALL.len()],
+    flags: Flags,
+}
+
+impl<W: Write> Tracer<W> {
+    /// Starts tracing with the current state of the emulator.
+    pub fn new(writer: W, emulator: &Emulator) -> Tracer<W> {
+        Tracer {
+            writer,
+            registers: IRRegister::ALL.map(|register| emulator.register(register)),
+            flags: emulator.flags,
+        }
+    }
+
+    /// Writes the line of `step`, which was just executed by `emulator`.
+    pub fn record(&mut self, emulator: &Emulator, step: &Step) -> io::Result<()> {
+        let mut changes = Vec::new();
+        for (index, &register) in IRRegister::ALL.iter().enumerate() {
+            let value = emulator.register(register);
+            if value != self.registers[index] && register != IRRegister::IP {
+                changes.push(format!("{}={}", register.name(), value));
+            }
+            self.registers[index] = value;
+        }
+        if emulator.flags.zero != self.flags.zero {
+            changes.push(format!("Z={}", emulator.flags.zero as u8));
+        }
+        if emulator.flags.signed != self.flags.signed {
+            changes.push(format!("S={}", emulator.flags.signed as u8));
+        }
+        self.flags = emulator.flags;
+        changes.extend(step.writes.iter().map(|(address, value)| format!("[0x{:04x}]={}", address, value)));
+
+        if changes.is_empty() {
+            writeln!(self.writer, "{:04x}: {}", step.address, step.instruction)
+        } else {
+            writeln!(self.writer, "{:04x}: {} ; {}", step.address, step.instruction, changes.join(" "))
+        }
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+/// First difference between two traces.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Divergence {
+    /// Line number (starting at 1) of the first line which differs.
+    pub line: usize,
+    /// Last line both traces have in common.
+    pub common: Option<String>,
+    /// Lines of both traces, `None` if the trace ended before.
+    pub left: Option<String>,
+    pub right: Option<String>,
+}
+
+pub fn read_trace<P: AsRef<Path>>(path: P) -> io::Result<Vec<String>> {
+    BufReader::new(File::open(path)?).lines().collect()
+}
+
+/// Finds the first line where the traces differ. Returns `None` if they are equal.
+pub fn diff(left: &[String], right: &[String]) -> Option<Divergence> {
+    let index = (0..left.len().max(right.len())).find(|&index| left.get(index) != right.get(index))?;
+    Some(Divergence {
+        line: index + 1,
+        common: index.checked_sub(1).map(|previous| left[previous].clone()),
+        left: left.get(index).cloned(),
+        right: right.get(index).cloned(),
+    })
+}
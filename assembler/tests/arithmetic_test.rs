@@ -0,0 +1,53 @@
+use lib::arithmetic::Operation::{self, *};
+
+#[test]
+fn edge_cases() {
+    let table: &[(Operation, i32, i32, i32)] = &[
+        (Add, i32::MAX, 1, i32::MIN),
+        (Sub, i32::MIN, 1, i32::MAX),
+        (Mul, 0x10000, 0x10000, 0),
+        (Mul, i32::MIN, -1, i32::MIN),
+        (Div, 7, 2, 3),
+        (Div, -7, 2, -3),
+        (Div, 7, -2, -3),
+        (Div, 7, 0, 0),
+        (Div, i32::MIN, -1, i32::MIN),
+        (Mod, 7, 3, 1),
+        (Mod, -7, 3, -1),
+        (Mod, 7, -3, 1),
+        (Mod, 7, 0, 0),
+        (Mod, i32::MIN, -1, 0),
+        (Pow, 2, 10, 1024),
+        (Pow, 2, 31, i32::MIN),
+        (Pow, 2, 32, 0),
+        (Pow, 3, 0, 1),
+        (Pow, 0, 0, 1),
+        (Pow, 2, -1, 0),
+        (Pow, 1, -1, 0),
+        (Shl, 1, 31, i32::MIN),
+        (Shl, 1, 32, 1),
+        (Shl, 1, -1, i32::MIN),
+        (Shr, -8, 1, -4),
+        (Shr, i32::MIN, 31, -1),
+        (Shr, 8, 33, 4),
+        (And, -1, 0x0f, 0x0f),
+        (Or, 0x0f, 0xf0, 0xff),
+        (Xor, -1, 0x0f, -16),
+    ];
+
+    let failures = table
+        .iter()
+        .filter(|&&(operation, a, b, expected)| operation.apply(a, b) != expected)
+        .map(|&(operation, a, b, expected)| format!("{} {} {} = {}, expected {}", a, operation.symbol(), b, operation.apply(a, b), expected))
+        .collect::<Vec<_>>();
+    assert!(failures.is_empty(), "{:#?}", failures);
+}
+
+#[test]
+fn symbols() {
+    for operation in Operation::ALL {
+        assert_eq!(Operation::from_symbol(operation.symbol()), Some(operation));
+    }
+    assert_eq!(Operation::from_symbol("/"), Some(Div));
+    assert_eq!(Operation::from_symbol("NOT"), None);
+}
@@ -0,0 +1,56 @@
+mod common;
+
+use lib::{
+    blueprint::Blueprint,
+    circuit::{Circuit, Signals, WireColor},
+};
+
+fn load(path: &str) -> Circuit {
+    Circuit::new(&Blueprint::read(common::source_path(path)).unwrap()).unwrap()
+}
+
+fn signals(values: &[(&str, i32)]) -> Signals {
+    values.iter().map(|&(signal, value)| (format!("signal-{}", signal), value)).collect()
+}
+
+#[test]
+fn feedback_loop() {
+    // C counts from 1 to 10 on the input of the decider, then it is reset.
+    let mut circuit = load("tests/circuit/clock.txt");
+    for tick in 0..25 {
+        assert_eq!(circuit.signals(2, 1), signals(&[("C", tick % 10 + 1)]), "tick {}", tick);
+        circuit.tick();
+    }
+}
+
+#[test]
+fn latency_and_summing() {
+    let mut circuit = load("tests/circuit/chain.txt");
+    assert_eq!(circuit.output(2), Some(&Signals::new()));
+
+    circuit.tick();
+    assert_eq!(circuit.output(2), Some(&signals(&[("X", 42)])));
+    // The output of the first arithmetic combinator and the constant combinator behind the pole.
+    assert_eq!(circuit.network(3, 1, WireColor::Green), signals(&[("X", 142)]));
+    assert_eq!(circuit.network(3, 1, WireColor::Red), Signals::new());
+
+    circuit.run(2);
+    assert_eq!(circuit.output(3), Some(&signals(&[("X", 284)])));
+    assert_eq!(circuit.output(5), Some(&signals(&[("X", 284)])));
+
+    circuit.set_constant(1, signals(&[("A", -6)]));
+    circuit.run(3);
+    assert_eq!(circuit.output(5), Some(&Signals::new()));
+    assert_eq!(circuit.ticks, 6);
+}
+
+#[test]
+fn blueprint_strings() {
+    let blueprint = Blueprint::read(common::source_path("tests/circuit/chain.txt")).unwrap();
+    assert_eq!(blueprint.label.as_deref(), Some("Chain"));
+    assert_eq!(blueprint.entities.len(), 6);
+    assert_eq!(Blueprint::decode(&blueprint.encode()), Ok(blueprint));
+
+    assert!(Blueprint::decode("1abc").is_err());
+    assert!(Blueprint::decode("0not base64").is_err());
+}
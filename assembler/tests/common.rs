@@ -1,17 +1,36 @@
+// Not every test binary uses every helper.
+#![allow(dead_code)]
+
 use std::{
-    cmp, fs,
+    cmp, collections::HashMap, fs,
     path::PathBuf,
 };
 
 use colored::Colorize;
-use lib::assemble;
+use lib::{
+    assemble, assemble_object,
+    disassembler::disassemble,
+    emulator::Emulator,
+    ir::{parse_number, IRDirective, IRInstruction, IRLine, IRParameter, IRRegister, IRTranslationTable},
+    link,
+    microarchitecture::{DecodeModel, DecodedSignals},
+    LinkOptions, Program, PROGRAM_START,
+};
 
-fn source_path(path: &str) -> PathBuf {
+/// Instructions a test program may execute before it has to halt.
+const MAX_TEST_STEPS: u64 = 100_000;
+
+pub fn source_path(path: &str) -> PathBuf {
     let mut buf = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
     buf.push(path);
     buf
 }
 
+/// Assembles and links a single source file with the default options.
+pub fn link_source(path: &str) -> Program {
+    link(&[assemble_object(source_path(path))], &LinkOptions::default())
+}
+
 pub fn assemble_test(asm_file: &str, expected_output_file: &str) {
     let actual = assemble(source_path(asm_file));
     let expected = fs::read(source_path(expected_output_file)).unwrap();
@@ -26,6 +45,228 @@ pub fn assemble_test(asm_file: &str, expected_output_file: &str) {
     }
 }
 
+/// Disassembles the binary and checks that the result assembles to the same binary.
+pub fn disassemble_test(bin_file: &str) {
+    let expected = fs::read(source_path(bin_file)).unwrap();
+    let assembly = disassemble(&expected, PROGRAM_START, None);
+
+    let mut asm_file = PathBuf::from(env!("CARGO_TARGET_TMPDIR"));
+    asm_file.push(bin_file.replace(['/', '\\'], "_"));
+    asm_file.set_extension("asm");
+    fs::write(&asm_file, &assembly).unwrap();
+    let actual = assemble(&asm_file);
+
+    if expected != actual {
+        println!("Disassemble Test Failed");
+        println!("Binary: {}", bin_file);
+        println!("Disassembly:\n{}", assembly);
+        print_difference(&expected, &actual);
+
+        assert_eq!(expected, actual);
+    }
+}
+
+/// Instruction type as documented in the instruction tables of Architecture.md.
+struct DocumentedEncoding {
+    opcode: u8,
+    /// Operands in the bytes left of the type, from right to left. `None` is the jump location.
+    first_word: Vec<Option<usize>>,
+    /// Additional values after the first one.
+    words: usize,
+    /// The explanation assigns the first operand, e.g. `reg += imm` or `Copy imm to reg.`.
+    writes_first: bool,
+}
+
+/// Rows of the instruction tables in Architecture.md, by mnemonic and operand kinds, e.g. `("MOV", ["reg", "[imm]"])`.
+fn documented_encodings() -> HashMap<(String, Vec<String>), DocumentedEncoding> {
+    let document = fs::read_to_string(source_path("../Architecture.md")).unwrap();
+    let mut encodings = HashMap::new();
+    for row in document.lines().filter(|line| line.starts_with("| `")) {
+        // | `Instruction` | Encoding | additional values... | Dec | Explanation
+        let columns = row.split('|').map(str::trim).collect::<Vec<_>>();
+        let (instruction, encoding, explanation) = (columns[1].trim_matches('`'), columns[2], columns[columns.len() - 1]);
+        let (mnemonic, operands) = instruction.split_once(' ').unwrap_or((instruction, ""));
+        let operands = operands.split(',').map(str::trim).filter(|operand| !operand.is_empty()).collect::<Vec<_>>();
+
+        let mut bytes = encoding.split_whitespace().collect::<Vec<_>>();
+        let opcode = u8::from_str_radix(bytes.pop().unwrap(), 16).unwrap();
+        let first_word = bytes
+            .iter()
+            .rev()
+            .map(|&byte| (byte != "location").then(|| operands.iter().position(|operand| operand.trim_matches(['[', ']']) == byte).unwrap()))
+            .collect();
+        let writes_first = operands.first().is_some_and(|&operand| {
+            let assigned = explanation.strip_prefix(operand).is_some_and(|rest| {
+                ["=", "+=", "-=", "*=", "/=", "%=", "&=", "¦=", "^=", "<<=", ">>=", "++", "--"].iter().any(|assignment| rest.trim_start().starts_with(assignment))
+            });
+            assigned || explanation.contains(&format!("to {}.", operand)) || explanation.contains(&format!("into {}", operand))
+        });
+
+        let kinds = operands.iter().map(|operand| operand.replace(['₁', '₂'], "").replace("immb", "imm")).collect();
+        encodings.insert(
+            (mnemonic.to_string(), kinds),
+            DocumentedEncoding {
+                opcode,
+                first_word,
+                words: columns[3..columns.len() - 2].iter().filter(|column| !column.is_empty()).count(),
+                writes_first,
+            },
+        );
+    }
+    encodings
+}
+
+/// Checks the signals the decode model derives from every instruction word in the binary against
+/// the instructions in the source, encoded as documented in Architecture.md.
+pub fn decode_test(asm_file: &str, bin_file: &str) {
+    let source = fs::read_to_string(source_path(asm_file)).unwrap();
+    let translation = IRTranslationTable::new();
+    let lines = source.lines().enumerate().filter_map(|(number, line)| translation.create_intermediate(line, number + 1)).collect::<Vec<_>>();
+    let words = fs::read(source_path(bin_file)).unwrap().chunks_exact(4).map(|word| u32::from_be_bytes(word.try_into().unwrap())).collect::<Vec<_>>();
+    let encodings = documented_encodings();
+    let model = DecodeModel::new();
+
+    let encoding_of = |instruction: &IRInstruction| {
+        let kinds = [&instruction.param1, &instruction.param2]
+            .into_iter()
+            .flatten()
+            .map(|param| match param {
+                IRParameter::Reg(_) => "reg",
+                IRParameter::MemReg(_) => "[reg]",
+                IRParameter::Label(_) if instruction.command.is_relative_jump() => "label",
+                IRParameter::Imm(_) | IRParameter::Label(_) => "imm",
+                IRParameter::MemImm(_) | IRParameter::MemLabel(_) => "[imm]",
+            })
+            .map(String::from)
+            .collect::<Vec<_>>();
+        let key = (instruction.command.mnemonic().to_string(), kinds);
+        encodings.get(&key).unwrap_or_else(|| panic!("{:?} is not documented in Architecture.md", key))
+    };
+
+    // Word offset of every line.
+    let mut offsets = Vec::new();
+    let mut labels = HashMap::new();
+    let mut offset = 0;
+    for line in &lines {
+        offsets.push(offset);
+        match line {
+            IRLine::Ins(instruction) => offset += 1 + encoding_of(instruction).words,
+            IRLine::Label(label) => {
+                labels.insert(label.as_str(), offset as i32);
+            }
+            IRLine::Directive(IRDirective::Word(values), _) => offset += values.len(),
+            IRLine::Directive(IRDirective::Space(count), _) => offset += *count as usize,
+            IRLine::Directive(..) => {}
+        }
+    }
+
+    for (line, offset) in lines.iter().zip(offsets) {
+        let instruction = match line {
+            IRLine::Ins(instruction) => instruction,
+            _ => continue,
+        };
+        let encoding = encoding_of(instruction);
+        let params = [&instruction.param1, &instruction.param2].into_iter().flatten().collect::<Vec<_>>();
+
+        let mut expected = DecodedSignals {
+            i: encoding.opcode as i32,
+            ..DecodedSignals::default()
+        };
+        let mut bytes = [0; 2];
+        for (index, operand) in encoding.first_word.iter().enumerate() {
+            match operand.map(|operand| params[operand]) {
+                Some(IRParameter::Reg(register) | IRParameter::MemReg(register)) => bytes[index] = *register as i32,
+                Some(IRParameter::Imm(value)) => bytes[index] = *value as u8 as i32,
+                None => match params[0] {
+                    IRParameter::Label(label) => expected.v = labels[label.as_str()] - offset as i32,
+                    param => panic!("Jump location {:?} is not a label", param),
+                },
+                Some(param) => panic!("{:?} does not fit into a byte", param),
+            }
+        }
+        expected.a = bytes[0];
+        expected.b = bytes[1];
+        if !instruction.command.is_relative_jump() {
+            expected.v = bytes[1] << 8 | bytes[0];
+        }
+        if let (true, Some(IRParameter::Reg(register))) = (encoding.writes_first, &instruction.param1) {
+            expected.x = *register as i32;
+        }
+
+        let actual = model.decode(words[offset]);
+        if actual != Some(expected) {
+            println!("Decode Test Failed");
+            println!("Source: {}:{}", asm_file, instruction.line_number);
+            println!("Word: 0x{:08x}", words[offset]);
+            assert_eq!(Some(expected), actual);
+        }
+    }
+}
+
+/// Runs the program and checks the `; expect: ...` comments in its source.
+/// An expectation is a comma separated list of:
+///
+/// * `A = 5` - final value of a register (A, B, C, D, IP, SP)
+/// * `Z = 1` - final value of a flag (Z, S)
+/// * `[0x29] = 37` - final value at a memory address
+/// * `out[0x10000] = 3 2 1` - all values written to an (output) address, in order
+pub fn run_test(asm_file: &str) {
+    let source = fs::read_to_string(source_path(asm_file)).unwrap();
+    let expectations = source
+        .lines()
+        .filter_map(|line| line.split_once(';')?.1.trim_start().strip_prefix("expect:"))
+        .flat_map(|expectations| expectations.split(','))
+        .map(|expectation| {
+            let (target, value) = expectation.split_once('=').unwrap_or_else(|| panic!("Invalid expectation '{}'", expectation));
+            (target.trim().to_string(), value.trim().to_string())
+        })
+        .collect::<Vec<_>>();
+
+    let program = link_source(asm_file);
+    let mut emulator = Emulator::with_program(&program);
+    let mut outputs: HashMap<i32, Vec<i32>> = HashMap::new();
+    let halted = emulator.run_with(MAX_TEST_STEPS, |_, step| {
+        for &(address, value) in &step.writes {
+            outputs.entry(address).or_default().push(value);
+        }
+    });
+    assert_eq!(halted, Ok(true), "Program did not halt: {}", asm_file);
+
+    let number = |value: &str| parse_number(value).unwrap_or_else(|| panic!("Invalid number '{}' in expectation", value));
+    let mut failures = Vec::new();
+    for (target, value) in expectations {
+        let (expected, actual) = if let Some(address) = target.strip_prefix("out[").and_then(|target| target.strip_suffix(']')) {
+            let expected = value.split_whitespace().map(number).collect::<Vec<_>>();
+            (expected, outputs.remove(&number(address)).unwrap_or_default())
+        } else if let Some(address) = target.strip_prefix('[').and_then(|target| target.strip_suffix(']')) {
+            (vec![number(&value)], vec![emulator.read(number(address))])
+        } else {
+            let actual = match target.as_str() {
+                "Z" => emulator.flags.zero as i32,
+                "S" => emulator.flags.signed as i32,
+                register => {
+                    let register = IRRegister::ALL.into_iter().find(|candidate| candidate.name() == register);
+                    emulator.register(register.unwrap_or_else(|| panic!("Unknown register '{}' in expectation", target)))
+                }
+            };
+            (vec![number(&value)], vec![actual])
+        };
+
+        if expected != actual {
+            failures.push(format!("{}: expected {:?}, got {:?}", target, expected, actual));
+        }
+    }
+
+    if !failures.is_empty() {
+        println!("Run Test Failed");
+        println!("Source: {}", asm_file);
+        for failure in &failures {
+            println!("{}", failure);
+        }
+        panic!("{} expectation(s) failed", failures.len());
+    }
+}
+
 fn print_difference(expected: &[u8], actual: &[u8]) {
     const BYTES_PER_ROW: usize = 8;
     const COLUMN_WIDTH: usize = 3 * BYTES_PER_ROW;
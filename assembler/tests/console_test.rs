@@ -0,0 +1,50 @@
+mod common;
+
+use lib::{
+    assemble,
+    console::{self, Anchor},
+    rom::RomPlan,
+    PROGRAM_START,
+};
+
+fn rom(binary: &[u8], slots: usize) -> RomPlan {
+    RomPlan::new(binary, PROGRAM_START, slots)
+}
+
+#[test]
+fn rom_loader_contains_program_words() {
+    let binary = assemble(common::source_path("tests/data/misc/ee_halt.asm"));
+    let command = console::rom_loader(&rom(&binary, 1), &Anchor::Player);
+    assert!(command.starts_with("/c local rows = {{238}} local signals = {\"signal-green\"} "), "{}", command);
+    assert!(!command.contains('\n'));
+    assert!(command.contains("game.player.selected"));
+
+    let negative = console::rom_loader(&rom(&[0xFF, 0xFF, 0xFF, 0xFD, 0, 0, 0, 7], 1), &Anchor::Player);
+    assert!(negative.starts_with("/c local rows = {{-3}, {7}} "), "{}", negative);
+}
+
+#[test]
+fn rom_loader_starts_at_map_tag() {
+    let command = console::rom_loader(&rom(&[], 1), &Anchor::Tag("rom \"1\"\nbank\\".into()));
+    assert!(command.contains(r#"local tag_text = "rom \"1\"\nbank\\" "#), "{}", command);
+    assert!(command.contains("find_chart_tags"));
+    assert!(!command.contains("game.player.selected"));
+}
+
+#[test]
+fn rom_loader_only_writes_combinators_at_rom_positions() {
+    let command = console::rom_loader(&rom(&[0, 0, 0, 1, 0, 0, 0, 2], 1), &Anchor::Player);
+    // Every cell is looked up at its position in the row and checked before the first write.
+    assert!(command.contains("position = {x = origin.x + i - 1, y = origin.y}"), "{}", command);
+    assert!(!command.contains("area ="), "{}", command);
+    let (lookup, write) = (command.find("error(\"Found \"").unwrap(), command.find("set_signal").unwrap());
+    assert!(lookup < write);
+}
+
+#[test]
+fn rom_loader_packs_words_like_the_blueprint() {
+    let binary = [1, 2, 3, 4, 5].iter().flat_map(|word: &i32| word.to_be_bytes()).collect::<Vec<_>>();
+    let command = console::rom_loader(&rom(&binary, 2), &Anchor::Player);
+    assert!(command.starts_with("/c local rows = {{1, 2}, {3, 4}, {5}} local signals = {\"signal-5\", \"signal-6\"} "), "{}", command);
+    assert!(command.contains("Loaded 5 words into"), "{}", command);
+}
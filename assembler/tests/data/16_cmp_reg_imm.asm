@@ -1,2 +1,3 @@
 MOV A, 5
 CMP A, 42 ; A = 5 (unchanged)
+; expect: A = 5
@@ -1,3 +1,4 @@
 MOV A, 42
 MOV B, 5
 CMP A, B ; A = 42 (unchanged)
+; expect: A = 42
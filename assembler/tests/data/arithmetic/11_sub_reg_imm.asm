@@ -1 +1,2 @@
 SUB D, 0x11223344 ; D = 0xEEDDCCBC = -287454020
+; expect: D = 0xEEDDCCBC
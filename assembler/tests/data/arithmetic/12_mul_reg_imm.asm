@@ -1,2 +1,3 @@
 MOV B, 5
 MUL B, 3 ; B = 0x0f = 15
+; expect: B = 0x0f
@@ -1,2 +1,3 @@
 MOV C, 0x130
 DIV C, 0x13 ; C = 0x10 = 16
+; expect: C = 0x10
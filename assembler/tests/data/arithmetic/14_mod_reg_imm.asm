@@ -1,2 +1,3 @@
 MOV B, 11
 MOD B, 3 ; B = 2
+; expect: B = 2
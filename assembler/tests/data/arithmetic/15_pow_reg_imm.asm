@@ -1,2 +1,3 @@
 MOV D, 2
 POW D, 9 ; D = 512
+; expect: D = 512
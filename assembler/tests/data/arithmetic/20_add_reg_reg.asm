@@ -1,3 +1,4 @@
 MOV B, 5
 MOV A, 7
 ADD A, B ; A = 0x0C = 12
+; expect: A = 0x0C
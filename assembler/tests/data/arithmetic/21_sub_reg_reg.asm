@@ -1,3 +1,4 @@
 MOV D, 13
 MOV C, 6
 SUB D, C ; D = 7
+; expect: D = 7
@@ -1,3 +1,4 @@
 MOV A, 3
 MOV SP, 0x10
 MUL A, SP ; A = 0x30 = 48
+; expect: A = 0x30
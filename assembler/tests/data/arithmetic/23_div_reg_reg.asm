@@ -1,2 +1,3 @@
 MOV SP, 0x777
 DIV SP, SP ; SP = 1
+; expect: SP = 1
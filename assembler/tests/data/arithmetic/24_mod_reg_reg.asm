@@ -1,3 +1,4 @@
 MOV B, 23
 MOV A, 5
 MOD B, A ; B = 3
+; expect: B = 3
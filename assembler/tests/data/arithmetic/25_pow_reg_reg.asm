@@ -1,3 +1,4 @@
 MOV D, 3
 MOV A, 4
 POW D, A ; D = 0x51 = 81
+; expect: D = 0x51
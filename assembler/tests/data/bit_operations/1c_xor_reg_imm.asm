@@ -1,2 +1,3 @@
 MOV A, 0x7F05
 XOR A, 0x5FF1 ; A = 0x20F4 = 8436
+; expect: A = 0x20F4
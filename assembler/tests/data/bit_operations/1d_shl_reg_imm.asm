@@ -1,2 +1,3 @@
 MOV A, 0x0F21
 SHL A, 2 ; A = 0x3C84 = 15492
+; expect: A = 0x3C84
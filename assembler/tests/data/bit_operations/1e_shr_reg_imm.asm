@@ -1,2 +1,3 @@
 MOV A, 0x3C84
 SHR A, 2 ; A = 0x0F21 = 3873
+; expect: A = 0x0F21
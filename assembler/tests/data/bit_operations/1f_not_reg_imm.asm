@@ -1,2 +1,3 @@
 MOV A, 0xFFFFFF3C
 NOT A ; A = 0xC3 = 195
+; expect: A = 0xC3
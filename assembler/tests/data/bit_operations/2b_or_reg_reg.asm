@@ -1,3 +1,4 @@
 MOV C, 0x555
 MOV D, 0x333
 OR  D, C ; D = 0x777 = 1911
+; expect: D = 0x777
@@ -1,3 +1,4 @@
 MOV A, 0x555
 MOV C, 0x333
 XOR A, C ; A = 0x666 = 1638
+; expect: A = 0x666
@@ -1,3 +1,4 @@
 MOV A, 0xFFFFFFFF
 MOV B, 1
 SHL A, B ; A = 0xFFFFFFFE = -2
+; expect: A = 0xFFFFFFFE
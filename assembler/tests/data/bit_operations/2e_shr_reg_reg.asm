@@ -1,3 +1,4 @@
 MOV A, 0xFFFFFFFF
 MOV B, 5
 SHR A, B ; A = 0xFFFFFFFF = -1
+; expect: A = 0xFFFFFFFF
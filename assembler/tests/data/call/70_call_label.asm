@@ -1,6 +1,7 @@
 CALL f1
 CALL f1
 HALT ; A = 84
+; expect: A = 84
 
 ; Functions
 f1:
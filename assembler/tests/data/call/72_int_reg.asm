@@ -9,6 +9,7 @@ start:
     INT A
     INT A
     HALT ; A = 3, B = 42
+    ; expect: A = 3, B = 42
 
 ; Functions
 i1:
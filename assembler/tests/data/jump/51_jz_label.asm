@@ -7,3 +7,4 @@ skip:
     MOV C, 1
 skip2:
     HALT ; B = 0, C = 1
+    ; expect: B = 0, C = 1
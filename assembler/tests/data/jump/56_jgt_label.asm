@@ -7,3 +7,4 @@ skip:
     MOV C, 1
 skip2:
     HALT ; B = 1, C = 0
+    ; expect: B = 1, C = 0
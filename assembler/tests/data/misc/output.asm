@@ -0,0 +1,7 @@
+; Counts down on an output address.
+MOV A, 3
+loop:
+    MOV [0x10000], A
+    DEC A
+    JNZ loop
+HALT ; expect: out[0x10000] = 3 2 1, A = 0, Z = 1, S = 0
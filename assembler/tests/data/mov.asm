@@ -50,3 +50,5 @@ HALT
 ; [0x2B] 0x26 (Dec 38)
 ; [0x2C] 0x2A (Dec 42)
 ; [0x2D] 0x25 (Dec 37)
+; expect: [0x25] = 0x23, [0x26] = 0x24, [0x27] = 0x25, [0x28] = 0x26, [0x29] = 0x25
+; expect: [0x2A] = 0x13, [0x2B] = 0x26, [0x2C] = 0x2A, [0x2D] = 0x25
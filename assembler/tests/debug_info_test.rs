@@ -0,0 +1,28 @@
+mod common;
+
+use lib::{assemble_with_debug_info, debug_info::DebugInfo};
+
+#[test]
+fn labels_and_lines() {
+    let (_, debug_info) = assemble_with_debug_info(common::source_path("tests/data/jump/55_jle_label.asm"));
+
+    assert_eq!(debug_info.symbol("skip"), Some(6));
+    assert_eq!(debug_info.symbol("skip2"), Some(11));
+    assert_eq!(debug_info.symbol("missing"), None);
+
+    // CMP A, 0 takes two words.
+    let first = &debug_info.lines[0];
+    assert_eq!((first.line, first.start, first.end), (1, 1, 3));
+
+    assert!(debug_info.location(11).unwrap().ends_with("55_jle_label.asm:9"));
+    assert_eq!(debug_info.symbol_before(10).unwrap().name, "skip");
+}
+
+#[test]
+fn json_roundtrip() {
+    let (_, debug_info) = assemble_with_debug_info(common::source_path("tests/data/call/70_call_label.asm"));
+
+    let path = std::env::temp_dir().join("factorio_cpu_debug_info_test.json");
+    debug_info.write(&path).unwrap();
+    assert_eq!(DebugInfo::read(&path).unwrap(), debug_info);
+}
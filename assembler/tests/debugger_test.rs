@@ -0,0 +1,84 @@
+mod common;
+
+use lib::{debugger::Debugger, emulator::Emulator, ir::IRRegister};
+
+fn debugger(path: &str) -> Debugger {
+    let program = common::link_source(path);
+    Debugger::new(Emulator::with_program(&program), &program)
+}
+
+fn ip(debugger: &Debugger) -> i32 {
+    debugger.emulator().register(IRRegister::IP)
+}
+
+#[test]
+fn breakpoints() {
+    let mut debugger = debugger("tests/emulator/sum.asm");
+
+    assert_eq!(debugger.execute("break add").unwrap(), "Breakpoint at 0x000d <add>");
+    assert!(debugger.execute("continue").unwrap().starts_with("Breakpoint\n0x000d <add> at "));
+    assert!(debugger.execute("continue").unwrap().ends_with("   13 |     PUSH B"));
+    assert_eq!(debugger.emulator().register(IRRegister::A), 10);
+
+    // Lines of the source file.
+    debugger.execute("delete").unwrap();
+    debugger.execute("b sum.asm:10").unwrap();
+    assert!(debugger.execute("c").unwrap().starts_with("Breakpoint\n0x000c <loop+5>"));
+    assert_eq!(debugger.emulator().register(IRRegister::A), 55);
+
+    assert!(debugger.execute("break nowhere").unwrap().starts_with("Unknown"));
+}
+
+#[test]
+fn stepping() {
+    let mut debugger = debugger("tests/emulator/sum.asm");
+
+    debugger.execute("b 6").unwrap();
+    debugger.execute("c").unwrap();
+    assert_eq!(ip(&debugger), 7);
+
+    // Steps into the call.
+    debugger.execute("step").unwrap();
+    assert_eq!(ip(&debugger), 0xd);
+    debugger.execute("finish").unwrap();
+    assert_eq!(ip(&debugger), 8);
+    debugger.execute("n").unwrap();
+    // An empty line repeats the last command.
+    debugger.execute("").unwrap();
+    assert_eq!(ip(&debugger), 7);
+
+    // Steps over the call.
+    debugger.execute("next").unwrap();
+    assert_eq!(ip(&debugger), 8);
+    assert_eq!(debugger.emulator().register(IRRegister::A), 19);
+}
+
+#[test]
+fn memory_and_watchpoints() {
+    let mut debugger = debugger("tests/emulator/sum.asm");
+
+    debugger.execute("watch 0x80").unwrap();
+    assert!(debugger.execute("c").unwrap().starts_with("Watchpoint [0x0080] = 55\n"));
+    assert_eq!(debugger.execute("x 0x7f 2").unwrap(), "0x007f: 0\n0x0080: 55");
+    // CALL add
+    assert_eq!(debugger.execute("x loop").unwrap(), "0x0007 <loop>: 1648");
+    assert!(debugger.execute("c").unwrap().starts_with("Halted after 75 instructions"));
+}
+
+#[test]
+fn disassembly() {
+    let mut debugger = debugger("tests/emulator/sum.asm");
+
+    debugger.execute("b loop").unwrap();
+    debugger.execute("c").unwrap();
+    assert_eq!(debugger.execute("disas").unwrap(), "   \
+   0x0001  MOV SP, 256
+   0x0003  MOV A, 0
+   0x0005  MOV B, 10
+loop:
+=> 0x0007  CALL 0x000d <add>
+   0x0008  DEC B
+   0x0009  JNZ 0x0007 <loop>
+   0x000a  MOV [0x80], A
+   0x000c  HALT");
+}
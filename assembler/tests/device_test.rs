@@ -0,0 +1,54 @@
+mod common;
+
+use lib::{
+    device::{parse_device, reads_stdin, Keyboard, LampMatrix, NumericDisplay, Timer},
+    emulator::Emulator,
+    ir::IRRegister,
+};
+
+fn echo_emulator() -> Emulator {
+    let program = common::link_source("tests/devices/echo.asm");
+    let mut emulator = Emulator::with_program(&program);
+    emulator.attach(0x10000, Box::new(Keyboard::from_values([3, 7])));
+    emulator.attach(0x10001, Box::new(NumericDisplay::default()));
+    emulator.attach(0x10002, Box::new(Timer::default()));
+    emulator.attach(0x10010, Box::new(LampMatrix::new(4, 4)));
+    emulator
+}
+
+#[test]
+fn memory_mapped_devices() {
+    let mut emulator = echo_emulator();
+    assert_eq!(emulator.run(1000), Ok(true));
+
+    let states = emulator.devices().map(|(address, device)| (address, device.render())).collect::<Vec<_>>();
+    assert_eq!(states, [
+        (0x10000, None),
+        (0x10001, Some("7".into())),
+        (0x10002, None),
+        (0x10010, Some("#...\n.#..\n..#.\n...#".into())),
+    ]);
+    // Ticks of all instructions before reading the timer, with the default timing model.
+    assert_eq!(emulator.register(IRRegister::D), 137);
+    // Devices do not write to memory.
+    assert_eq!(emulator.read(0x10001), 0);
+}
+
+#[test]
+#[should_panic(expected = "overlaps")]
+fn overlapping_devices() {
+    let mut emulator = echo_emulator();
+    emulator.attach(0x1000e, Box::new(LampMatrix::new(8, 4)));
+}
+
+#[test]
+fn device_descriptions() {
+    assert_eq!(parse_device("lamps@0x100:8x2").map(|(address, device)| (address, device.size())), Ok((0x100, 2)));
+    assert_eq!(parse_device("timer@42").map(|(address, device)| (address, device.size())), Ok((42, 1)));
+    assert!(parse_device("lamps@0x100:40x2").is_err());
+    assert!(parse_device("number").is_err());
+    assert!(parse_device("speaker@1").is_err());
+    assert!(reads_stdin("keyboard@0x200"));
+    assert!(!reads_stdin("keyboard@0x200:input.txt"));
+    assert!(!reads_stdin("timer@42"));
+}
@@ -0,0 +1,20 @@
+; Copies keyboard input to the numeric display until the input ends
+; and draws a diagonal line on the lamp matrix.
+loop:
+    MOV A, [0x10000]
+    CMP A, 0
+    JZ draw
+    MOV [0x10001], A
+    JMP loop
+
+draw:
+    MOV B, 1
+    MOV C, 0x10010
+line:
+    MOV [C], B
+    INC C
+    SHL B, 1
+    CMP B, 0x10
+    JNZ line
+    MOV D, [0x10002]
+    HALT
@@ -0,0 +1,5 @@
+; Jumps into a table instead of reading from it.
+    JMP table
+    HALT
+table:
+    .word 1, 2
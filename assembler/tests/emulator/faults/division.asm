@@ -0,0 +1,7 @@
+; Halves the divisor until it is 0.
+    MOV A, 100
+    MOV B, 2
+loop:
+    DIV A, B
+    SHR B, 1
+    JMP loop
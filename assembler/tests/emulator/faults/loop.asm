@@ -0,0 +1,3 @@
+; Never halts.
+loop:
+    JMP loop
@@ -0,0 +1,4 @@
+; Calls an address which is not part of the program.
+    MOV A, 0x1000
+    INT A
+    HALT
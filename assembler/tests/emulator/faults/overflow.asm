@@ -0,0 +1,4 @@
+; Recursion without a base case.
+    MOV SP, 0x100
+recurse:
+    CALL recurse
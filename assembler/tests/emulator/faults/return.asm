@@ -0,0 +1,3 @@
+; Returns without being called.
+    MOV A, 1
+    RET
@@ -0,0 +1,4 @@
+; Pops from the empty stack.
+    MOV SP, 0x100
+    POP A
+    HALT
@@ -0,0 +1,5 @@
+; Reads a variable which was never written.
+    MOV [0x80], 1
+    MOV A, [0x80]
+    MOV B, [0x81]
+    HALT
@@ -0,0 +1,16 @@
+; Sums the numbers 1 to 10 with a function that uses the stack.
+    MOV SP, 0x100
+    MOV A, 0
+    MOV B, 10
+loop:
+    CALL add
+    DEC B
+    JNZ loop
+    MOV [0x80], A
+    HALT
+
+add:
+    PUSH B
+    POP C
+    ADD A, C
+    RET
@@ -0,0 +1,110 @@
+mod common;
+
+use std::ops::RangeInclusive;
+
+use lib::{emulator::{Checks, Emulator, Fault, Flags}, ir::IRRegister};
+
+fn run(path: &str) -> Emulator {
+    let program = common::link_source(path);
+    let mut emulator = Emulator::with_program(&program);
+    assert_eq!(emulator.run(1000), Ok(true));
+    emulator
+}
+
+#[test]
+fn call_and_stack() {
+    let emulator = run("tests/emulator/sum.asm");
+
+    assert_eq!(emulator.register(IRRegister::A), 55);
+    assert_eq!(emulator.register(IRRegister::SP), 0x100);
+    assert_eq!(emulator.read(0x80), 55);
+    assert_eq!(emulator.flags, Flags { zero: true, signed: false });
+}
+
+#[test]
+fn interrupts() {
+    let emulator = run("tests/data/call/72_int_reg.asm");
+
+    assert_eq!(emulator.register(IRRegister::A), 3);
+    assert_eq!(emulator.register(IRRegister::B), 42);
+}
+
+#[test]
+fn conditional_jumps() {
+    let emulator = run("tests/data/jump/55_jle_label.asm");
+    assert_eq!((emulator.register(IRRegister::B), emulator.register(IRRegister::C)), (0, 1));
+
+    let emulator = run("tests/data/jump/56_jgt_label.asm");
+    assert_eq!((emulator.register(IRRegister::B), emulator.register(IRRegister::C)), (1, 0));
+}
+
+#[test]
+fn arithmetic_shift_right() {
+    let emulator = run("tests/data/bit_operations/2e_shr_reg_reg.asm");
+
+    assert_eq!(emulator.register(IRRegister::A), -1);
+    assert_eq!(emulator.flags, Flags { zero: false, signed: true });
+}
+
+#[test]
+fn invalid_instruction() {
+    let mut emulator = Emulator::new();
+    emulator.load(1, &0x0000_0099u32.to_be_bytes());
+
+    assert_eq!(emulator.step(), Err(Fault::InvalidInstruction { address: 1, word: 0x99 }));
+}
+
+/// Runs the program with all checks and returns the fault with its source location.
+fn fault(path: &str, stack: Option<RangeInclusive<i32>>) -> (Fault, String) {
+    let program = common::link_source(path);
+    let mut emulator = Emulator::with_program(&program);
+    emulator.set_checks(Checks::all(&program, stack));
+    let fault = emulator.run(1000).unwrap_err();
+    let location = program.debug_info.location(fault.address() as u32).unwrap();
+    (fault, location.rsplit('/').next().unwrap().to_string())
+}
+
+#[test]
+fn arithmetic_faults() {
+    assert_eq!(fault("tests/emulator/faults/division.asm", None), (Fault::DivisionByZero { address: 5 }, "division.asm:5".into()));
+}
+
+#[test]
+fn stack_faults() {
+    let stack = Some(0xf0..=0x100);
+    assert_eq!(fault("tests/emulator/faults/overflow.asm", stack.clone()), (Fault::StackOverflow { address: 3, sp: 0xef }, "overflow.asm:4".into()));
+    assert_eq!(fault("tests/emulator/faults/underflow.asm", stack), (Fault::StackUnderflow { address: 3, sp: 0x100 }, "underflow.asm:3".into()));
+    assert_eq!(fault("tests/emulator/faults/return.asm", None), (Fault::ReturnWithEmptyStack { address: 3 }, "return.asm:3".into()));
+}
+
+#[test]
+fn memory_faults() {
+    assert_eq!(fault("tests/emulator/faults/uninitialized.asm", None), (Fault::UninitializedRead { address: 6, target: 0x81 }, "uninitialized.asm:4".into()));
+    assert_eq!(fault("tests/emulator/faults/data.asm", None), (Fault::ExecutingData { address: 3 }, "data.asm:5".into()));
+    assert_eq!(fault("tests/emulator/faults/outside.asm", None), (Fault::JumpOutsideImage { address: 3, target: 0x1000 }, "outside.asm:3".into()));
+}
+
+#[test]
+fn jump_outside_image_has_no_side_effects() {
+    let program = common::link_source("tests/emulator/faults/outside.asm");
+    let mut emulator = Emulator::with_program(&program);
+    emulator.set_checks(Checks::all(&program, None));
+    let sp = emulator.register(IRRegister::SP);
+
+    assert_eq!(emulator.run(1000), Err(Fault::JumpOutsideImage { address: 3, target: 0x1000 }));
+    // INT did not push the return address.
+    assert_eq!(emulator.register(IRRegister::IP), 3);
+    assert_eq!(emulator.register(IRRegister::SP), sp);
+    assert_eq!(emulator.read(sp), 0);
+}
+
+#[test]
+fn step_limit() {
+    assert_eq!(fault("tests/emulator/faults/loop.asm", None), (Fault::StepLimit { address: 1, steps: 1000 }, "loop.asm:3".into()));
+
+    // Without the check the program keeps running and the fault is not reported.
+    let program = common::link_source("tests/emulator/faults/division.asm");
+    let mut emulator = Emulator::with_program(&program);
+    assert_eq!(emulator.run(1000), Ok(false));
+    assert_eq!(emulator.register(IRRegister::A), 0);
+}
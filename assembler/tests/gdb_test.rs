@@ -0,0 +1,128 @@
+mod common;
+
+use std::{
+    io::{Read, Write},
+    net::{TcpListener, TcpStream},
+    thread,
+};
+
+use lib::{emulator::Emulator, gdb::GdbServer, ir::IRRegister};
+
+struct Client {
+    stream: TcpStream,
+}
+
+impl Client {
+    /// Sends a packet and returns the reply.
+    fn send(&mut self, packet: &str) -> String {
+        let checksum = packet.bytes().fold(0u8, |sum, byte| sum.wrapping_add(byte));
+        write!(self.stream, "${}#{:02x}", packet, checksum).unwrap();
+
+        let mut reply = Vec::new();
+        let mut byte = [0];
+        loop {
+            self.stream.read_exact(&mut byte).unwrap();
+            match byte[0] {
+                b'+' if reply.is_empty() => continue,
+                b'#' => break,
+                _ => reply.push(byte[0]),
+            }
+        }
+        let mut checksum = [0; 2];
+        self.stream.read_exact(&mut checksum).unwrap();
+        self.stream.write_all(b"+").unwrap();
+
+        assert_eq!(reply.remove(0), b'$');
+        String::from_utf8(reply).unwrap()
+    }
+}
+
+/// Starts a server for the program and connects to it. The server
+/// thread returns A and the memory at 0x80 after the session ended.
+fn connect(path: &str) -> (Client, thread::JoinHandle<(i32, i32)>) {
+    let program = common::link_source(path);
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap();
+    let server = thread::spawn(move || {
+        let mut server = GdbServer::new(Emulator::with_program(&program));
+        server.serve(&listener).unwrap();
+        (server.emulator().register(IRRegister::A), server.emulator().read(0x80))
+    });
+
+    let stream = TcpStream::connect(address).unwrap();
+    stream.set_nodelay(true).unwrap();
+    (Client { stream }, server)
+}
+
+#[test]
+fn target_description() {
+    let (mut client, server) = connect("tests/emulator/sum.asm");
+
+    assert!(client.send("qSupported:swbreak+").contains("qXfer:features:read+"));
+    let description = client.send("qXfer:features:read:target.xml:0,1000");
+    assert!(description.starts_with("l<?xml"));
+    assert_eq!(description.matches("<reg ").count(), 7);
+    // Partial reads
+    assert!(client.send("qXfer:features:read:target.xml:0,10").starts_with('m'));
+    assert_eq!(client.send("?"), "S05");
+    assert_eq!(client.send("vMustReplyEmpty"), "");
+
+    assert_eq!(client.send("D"), "OK");
+    server.join().unwrap();
+}
+
+#[test]
+fn registers_and_memory() {
+    let (mut client, server) = connect("tests/emulator/sum.asm");
+
+    assert_eq!(client.send("g"), "00000000000000000000000000000000000000040000000000000000");
+    // MOV SP, 0x100
+    assert_eq!(client.send("m4,8"), "0000060100000100");
+    assert_eq!(client.send("m4,3"), "E01");
+    assert_eq!(client.send("m2,4"), "E01");
+
+    assert_eq!(client.send("s"), "S05");
+    assert_eq!(client.send("p5"), "00000400");
+    assert_eq!(client.send("P0=0000002a"), "OK");
+    assert_eq!(client.send("P5=00000401"), "E01");
+    assert_eq!(client.send("P6=00000003"), "OK");
+    assert_eq!(client.send("M200,8:ffffffff00000007"), "OK");
+    assert_eq!(client.send("m1fc,c"), "00000000ffffffff00000007");
+    assert_eq!(client.send("g"), "0000002a0000000000000000000000000000000c0000040000000003");
+
+    assert_eq!(client.send("D"), "OK");
+    assert_eq!(server.join().unwrap(), (42, -1));
+}
+
+#[test]
+fn breakpoints() {
+    let (mut client, server) = connect("tests/emulator/sum.asm");
+
+    // Breakpoint at add, the word address 0xd is the byte address 0x34
+    assert_eq!(client.send("Z0,34,4"), "OK");
+    assert_eq!(client.send("Z0,35,4"), "E01");
+    assert_eq!(client.send("c"), "T05swbreak:;");
+    assert_eq!(client.send("p4"), "00000034");
+    assert_eq!(client.send("c"), "T05swbreak:;");
+    assert_eq!(client.send("p0"), "0000000a");
+
+    assert_eq!(client.send("z0,34,4"), "OK");
+    assert_eq!(client.send("c"), "W00");
+    assert_eq!(client.send("?"), "W00");
+
+    assert_eq!(client.send("k"), "OK");
+    assert_eq!(server.join().unwrap(), (55, 55));
+}
+
+#[test]
+fn fault_is_reported() {
+    let (mut client, server) = connect("tests/emulator/sum.asm");
+
+    // Replace the first instruction by an invalid word.
+    assert_eq!(client.send("M4,4:ffffffff"), "OK");
+    assert_eq!(client.send("s"), "S04");
+    assert_eq!(client.send("?"), "S04");
+
+    assert_eq!(client.send("k"), "OK");
+    server.join().unwrap();
+}
@@ -0,0 +1,158 @@
+use std::collections::BTreeMap;
+
+use lib::{
+    blueprint::{Blueprint, Connection, ControlBehavior, Entity, Position, Wire},
+    circuit::{Circuit, Point, Signals, WireColor},
+    generator,
+    microarchitecture::DecodeModel,
+    signal_policy::SignalPolicy,
+};
+
+#[test]
+fn decoder_raises_yellow_for_every_opcode() {
+    let opcodes = DecodeModel::new().opcodes();
+    let mut blueprint = generator::decoder_blueprint();
+    assert_eq!(blueprint.entities.len(), opcodes.len());
+    assert_eq!(Blueprint::decode(&blueprint.encode()).unwrap(), blueprint);
+
+    // Constant combinator driving I into the input of the first decider.
+    let input = blueprint.entities.len() as u32 + 1;
+    blueprint.entities.push(Entity {
+        entity_number: input,
+        name: "constant-combinator".into(),
+        position: Position { x: -0.5, y: 1.5 },
+        direction: None,
+        control_behavior: Some(ControlBehavior::default()),
+        connections: BTreeMap::from([(
+            "1".to_string(),
+            Connection {
+                red: vec![Wire { entity_id: 1, circuit_id: Some(1) }],
+                green: Vec::new(),
+            },
+        )]),
+    });
+    let mut circuit = Circuit::new(&blueprint).unwrap();
+
+    let yellow = Signals::from([("signal-yellow".to_string(), 1)]);
+    for &opcode in &opcodes {
+        circuit.set_constant(input, Signals::from([("signal-I".to_string(), opcode as i32)]));
+        circuit.tick();
+        for (index, &other) in opcodes.iter().enumerate() {
+            let expected = if other == opcode { yellow.clone() } else { Signals::new() };
+            assert_eq!(circuit.output(index as u32 + 1), Some(&expected), "I = {}, decider for {}", opcode, other);
+        }
+    }
+}
+
+/// Adds a constant combinator wired to the given connection point, returns its entity number.
+fn add_input(blueprint: &mut Blueprint, color: WireColor, (entity_id, point): (u32, Point)) -> u32 {
+    let wires = vec![Wire { entity_id, circuit_id: Some(point) }];
+    let (red, green) = match color {
+        WireColor::Red => (wires, Vec::new()),
+        WireColor::Green => (Vec::new(), wires),
+    };
+    let entity_number = blueprint.entities.len() as u32 + 1;
+    blueprint.entities.push(Entity {
+        entity_number,
+        name: "constant-combinator".into(),
+        position: Position { x: -5.5, y: entity_number as f64 + 0.5 },
+        direction: None,
+        control_behavior: Some(ControlBehavior::default()),
+        connections: BTreeMap::from([("1".to_string(), Connection { red, green })]),
+    });
+    entity_number
+}
+
+/// Constant combinator which starts the power-on pulse.
+fn pulse_constant(blueprint: &Blueprint) -> u32 {
+    blueprint.entities.iter().find(|entity| entity.name == "constant-combinator").unwrap().entity_number
+}
+
+fn entity_at(blueprint: &Blueprint, x: f64, y: f64) -> u32 {
+    blueprint.entities.iter().find(|entity| entity.position.x == x && entity.position.y == y).unwrap().entity_number
+}
+
+fn signal(name: &str, value: i32) -> Signals {
+    Signals::from([(format!("signal-{}", name), value)])
+}
+
+#[test]
+fn ram_reads_initial_data_and_writes() {
+    // Addresses 1 to 4, with 7 and -3 at 2 and 3.
+    let data = [7i32, -3].iter().flat_map(|word| word.to_be_bytes()).collect::<Vec<_>>();
+    let mut blueprint = generator::ram_blueprint(1, 4, 2, &data).unwrap();
+    let (latch, write, read) = (entity_at(&blueprint, 0.5, 1.0), entity_at(&blueprint, 0.5, 3.0), entity_at(&blueprint, 0.5, 5.0));
+    let write_address = add_input(&mut blueprint, WireColor::Red, (latch, 1));
+    let write_data = add_input(&mut blueprint, WireColor::Green, (write, 1));
+    let read_address = add_input(&mut blueprint, WireColor::Red, (read, 1));
+    let mut circuit = Circuit::new(&blueprint).unwrap();
+
+    // Nothing is loaded until the pulse is switched on.
+    circuit.run(3);
+    assert_eq!(circuit.network(latch, 2, WireColor::Green), Signals::new());
+    circuit.set_constant(pulse_constant(&blueprint), signal("pink", 1));
+
+    let read_word = |circuit: &mut Circuit, address| {
+        circuit.set_constant(read_address, signal("R", address));
+        circuit.tick();
+        circuit.network(read, 2, WireColor::Green)
+    };
+    circuit.run(3);
+    assert_eq!(read_word(&mut circuit, 1), Signals::new());
+    assert_eq!(read_word(&mut circuit, 2), signal("green", 7));
+    assert_eq!(read_word(&mut circuit, 3), signal("green", -3));
+
+    for (address, value) in [(4, 42), (2, 5)] {
+        circuit.set_constant(write_address, signal("W", address));
+        circuit.set_constant(write_data, signal("green", value));
+        circuit.tick();
+        circuit.set_constant(write_address, Signals::new());
+        circuit.set_constant(write_data, Signals::new());
+        circuit.run(5);
+    }
+    assert_eq!(read_word(&mut circuit, 4), signal("green", 42));
+    assert_eq!(read_word(&mut circuit, 2), signal("green", 5));
+    assert_eq!(read_word(&mut circuit, 3), signal("green", -3));
+
+    circuit.set_constant(write_address, signal("W", 1));
+    circuit.set_constant(write_data, signal("green", 1));
+    assert_eq!(SignalPolicy::default().check(&mut circuit, 10), Vec::new());
+}
+
+#[test]
+fn ram_rejects_data_outside_of_the_bank() {
+    assert!(generator::ram_blueprint(1, 2, 2, &[0; 8]).is_err());
+    assert!(generator::ram_blueprint(2, 2, 1, &[0; 4]).is_err());
+    assert!(generator::ram_blueprint(0, 2, 0, &[]).is_err());
+    assert!(generator::ram_blueprint(1, 2, 2, &[0; 4]).is_ok());
+}
+
+#[test]
+fn ram_does_not_change_while_it_is_built() {
+    let data = [7i32, -3].iter().flat_map(|word| word.to_be_bytes()).collect::<Vec<_>>();
+    let blueprint = generator::ram_blueprint(1, 4, 2, &data).unwrap();
+    let pulse = pulse_constant(&blueprint);
+    assert_eq!(blueprint.entities[pulse as usize - 1].control_behavior.as_ref().unwrap().is_on, Some(false));
+
+    // Bots build the entities one after another in any order, wires exist once both ends are built.
+    let numbers = blueprint.entities.iter().map(|entity| entity.entity_number).collect::<Vec<_>>();
+    let reversed = numbers.iter().rev().copied().collect::<Vec<_>>();
+    let pulse_first = [pulse].into_iter().chain(numbers.iter().copied().filter(|&number| number != pulse)).collect::<Vec<_>>();
+    for order in [numbers, reversed, pulse_first] {
+        for built in 1..=order.len() {
+            let mut partial = blueprint.clone();
+            partial.entities.retain(|entity| order[..built].contains(&entity.entity_number));
+            for entity in &mut partial.entities {
+                for connection in entity.connections.values_mut() {
+                    connection.red.retain(|wire| order[..built].contains(&wire.entity_id));
+                    connection.green.retain(|wire| order[..built].contains(&wire.entity_id));
+                }
+            }
+            let mut circuit = Circuit::new(&partial).unwrap();
+            circuit.run(5);
+            for network in 0..circuit.networks().len() {
+                assert_eq!(circuit.values(network), &Signals::new(), "{} of {:?} built", built, order);
+            }
+        }
+    }
+}
@@ -0,0 +1,2 @@
+MOV [0x10000], 1
+MOV A, [5000]
@@ -0,0 +1,46 @@
+mod common;
+
+use lib::{
+    assemble_object, link,
+    layout::{MemoryLayout, Region, RegionKind},
+    LinkOptions, Program,
+};
+
+fn link_with_layout(asm_file: &str, layout_file: &str) -> Program {
+    let layout = MemoryLayout::read(common::source_path(layout_file)).unwrap();
+    let options = LinkOptions { layout: Some(layout), ..LinkOptions::default() };
+    link(&[assemble_object(common::source_path(asm_file))], &options)
+}
+
+#[test]
+fn initialize_stack_pointer() {
+    let program = link_with_layout("tests/data/stack/61_push_reg.asm", "tests/layout/layout.json");
+    let expected: Vec<u8> = [
+        0x0000_0601, 144, // MOV SP, 144
+        0x0000_0401, 42, // MOV D, 42
+        0x0000_0461, // PUSH D
+        0x0000_00ee, // HALT
+    ].into_iter().flat_map(u32::to_be_bytes).collect();
+    assert_eq!(program.binary, expected);
+}
+
+#[test]
+#[should_panic(expected = "Program needs 4 words, but rom region 'rom' only has 2 words")]
+fn rom_overflow() {
+    link_with_layout("tests/data/stack/61_push_reg.asm", "tests/layout/tiny.json");
+}
+
+#[test]
+#[should_panic(expected = "Memory address 5000 on line 2")]
+fn memory_operand_outside_of_regions() {
+    link_with_layout("tests/layout/memory.asm", "tests/layout/layout.json");
+}
+
+#[test]
+fn overlapping_regions() {
+    let region = |name: &str, kind, start| Region { name: name.into(), kind, start, size: 10 };
+    let layout = MemoryLayout {
+        regions: vec![region("rom", RegionKind::Rom, 1), region("ram", RegionKind::Ram, 5)],
+    };
+    assert_eq!(layout.validate(), Err("Memory regions 'rom' and 'ram' overlap".into()));
+}
@@ -0,0 +1,6 @@
+; Uses the routines from math.asm
+.extern add42, value
+
+    MOV A, [value]
+    CALL add42
+    HALT ; A = 42 + 0xFF
@@ -0,0 +1,8 @@
+.global add42, value
+
+add42:
+    ADD A, 42
+    RET
+
+value:
+    NOP
@@ -0,0 +1,5 @@
+.extern quadruple
+
+    MOV A, 1
+    CALL quadruple
+    HALT ; A = 4
@@ -0,0 +1,19 @@
+; Every routine is placed in its own section,
+; so unused routines can be removed when linking.
+.global add42, double, quadruple
+
+.section .text.add42
+add42:
+    ADD A, 42
+    RET
+
+.section .text.double
+double:
+    MUL A, 2
+    RET
+
+.section .text.quadruple
+quadruple:
+    CALL double
+    CALL double
+    RET
@@ -0,0 +1,83 @@
+mod common;
+
+use lib::{
+    archive::{select_members, Archive},
+    assemble_object, link,
+    object::Object,
+    LinkOptions,
+};
+
+fn objects(files: &[&str]) -> Vec<Object> {
+    files.iter().map(|file| assemble_object(common::source_path(file))).collect()
+}
+
+#[test]
+fn link_objects() {
+    let program = link(&objects(&["tests/link/main.asm", "tests/link/math.asm"]), &LinkOptions::default());
+
+    let expected: Vec<u8> = [
+        0x0000_0103, 0x0000_0008, // MOV A, [value]
+        0x0000_0270, // CALL add42
+        0x0000_00ee, // HALT
+        0x0000_0110, 0x0000_002a, // add42: ADD A, 42
+        0x0000_0071, // RET
+        0x0000_00ff, // value: NOP
+    ].into_iter().flat_map(u32::to_be_bytes).collect();
+    assert_eq!(program.binary, expected);
+
+    let debug_info = program.debug_info;
+    assert_eq!(debug_info.files.len(), 2);
+    assert_eq!(debug_info.symbol("add42"), Some(5));
+    assert!(debug_info.location(6).unwrap().ends_with("math.asm:4"));
+}
+
+#[test]
+fn object_roundtrip() {
+    let object = assemble_object(common::source_path("tests/link/main.asm"));
+    assert_eq!(object.externs, ["add42", "value"]);
+    assert_eq!(object.sections[0].relocations.len(), 2);
+
+    let path = std::env::temp_dir().join("factorio_cpu_link_test.o");
+    object.write(&path).unwrap();
+    assert_eq!(Object::read(&path).unwrap(), object);
+}
+
+#[test]
+#[should_panic(expected = "Duplicate symbol 'add42'")]
+fn duplicate_symbol() {
+    link(&objects(&["tests/link/math.asm", "tests/link/math.asm"]), &LinkOptions::default());
+}
+
+#[test]
+#[should_panic(expected = "Undefined symbol 'add42'")]
+fn undefined_symbol() {
+    link(&objects(&["tests/link/main.asm"]), &LinkOptions::default());
+}
+
+#[test]
+fn archive_with_gc_sections() {
+    let archive = Archive { members: objects(&["tests/link/math.asm", "tests/link/routines.asm"]) };
+    let mut program = objects(&["tests/link/quadruple.asm"]);
+
+    let members = select_members(&program, &[archive]);
+    assert_eq!(members.len(), 1);
+    assert!(members[0].source.ends_with("routines.asm"));
+    program.extend(members);
+
+    let linked = link(&program, &LinkOptions { gc_sections: true, ..LinkOptions::default() });
+    let expected: Vec<u8> = [
+        0x0000_0101, 0x0000_0001, // MOV A, 1
+        0x0000_0570, // CALL quadruple
+        0x0000_00ee, // HALT
+        0x0000_0112, 0x0000_0002, // double: MUL A, 2
+        0x0000_0071, // RET
+        0xffff_fd70, // quadruple: CALL double
+        0xffff_fc70, // CALL double
+        0x0000_0071, // RET
+    ].into_iter().flat_map(u32::to_be_bytes).collect();
+    assert_eq!(linked.binary, expected);
+    assert_eq!(linked.debug_info.symbol("add42"), None);
+
+    let linked = link(&program, &LinkOptions::default());
+    assert_eq!(linked.binary.len(), expected.len() + 3 * 4);
+}
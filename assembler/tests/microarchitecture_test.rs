@@ -0,0 +1,61 @@
+mod common;
+
+use std::{
+    collections::BTreeSet,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use lib::microarchitecture::{DecodeModel, DecodedSignals};
+
+#[test]
+fn decoded_signals() {
+    let model = DecodeModel::new();
+
+    // MOV D, 42 (the example from Architecture.md)
+    assert_eq!(model.decode(0x0000_0401), Some(DecodedSignals { i: 0x01, v: 0x04, a: 4, b: 0, x: 4 }));
+    // MOV B, C
+    assert_eq!(model.decode(0x0003_0202), Some(DecodedSignals { i: 0x02, v: 0x0302, a: 2, b: 3, x: 2 }));
+    // MOV [A], B
+    assert_eq!(model.decode(0x0002_0108), Some(DecodedSignals { i: 0x08, v: 0x0201, a: 1, b: 2, x: 0 }));
+    // SHL A, 3
+    assert_eq!(model.decode(0x0003_011d), Some(DecodedSignals { i: 0x1d, v: 0x0301, a: 1, b: 3, x: 1 }));
+    // JMP -2
+    assert_eq!(model.decode(0xffff_fe50), Some(DecodedSignals { i: 0x50, v: -2, a: 0, b: 0, x: 0 }));
+    assert_eq!(model.decode(0x0000_0099), None);
+
+    let signals = model.decode(0x0000_0401).unwrap().signals();
+    assert_eq!(signals.into_iter().collect::<Vec<_>>(), [("signal-A".into(), 4), ("signal-I".into(), 1), ("signal-V".into(), 4), ("signal-X".into(), 4)]);
+}
+
+#[test]
+fn corpus_covers_all_opcodes() {
+    let model = DecodeModel::new();
+    let mut used = BTreeSet::new();
+    for bin_file in glob_bins(&common::source_path("tests/data")) {
+        let words = fs::read(bin_file).unwrap().chunks_exact(4).map(|word| u32::from_be_bytes(word.try_into().unwrap())).collect::<Vec<_>>();
+        let mut offset = 0;
+        while offset < words.len() {
+            let opcode = words[offset] as u8;
+            used.insert(opcode);
+            offset += model.size(opcode).unwrap() as usize;
+        }
+    }
+
+    let missing = model.opcodes().into_iter().filter(|opcode| !used.contains(opcode)).map(|opcode| format!("0x{:02x}", opcode)).collect::<Vec<_>>();
+    assert!(missing.is_empty(), "Instruction types without test data: {:?}", missing);
+}
+
+/// All binaries in the directory and its subdirectories.
+fn glob_bins(directory: &Path) -> Vec<PathBuf> {
+    let mut files = Vec::new();
+    for entry in fs::read_dir(directory).unwrap() {
+        let path = entry.unwrap().path();
+        if path.is_dir() {
+            files.extend(glob_bins(&path));
+        } else if path.extension().is_some_and(|extension| extension == "bin") {
+            files.push(path);
+        }
+    }
+    files
+}
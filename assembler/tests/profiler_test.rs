@@ -0,0 +1,39 @@
+mod common;
+
+use lib::{
+    emulator::Emulator,
+    profiler::{Cost, Profiler},
+};
+
+fn profile(path: &str) -> Profiler {
+    let program = common::link_source(path);
+    let mut emulator = Emulator::with_program(&program);
+    let mut profiler = Profiler::new(program.debug_info.clone(), program.start as i32);
+    assert_eq!(emulator.run_with(1000, |emulator, step| profiler.record(emulator, step)), Ok(true));
+    profiler
+}
+
+#[test]
+fn functions() {
+    let profiler = profile("tests/emulator/sum.asm");
+
+    // PUSH, POP, ADD and RET for each of the 10 calls.
+    assert_eq!(profiler.functions(), [
+        ("add".to_string(), Cost { instructions: 40, ticks: 180 }),
+        ("0x0001".to_string(), Cost { instructions: 35, ticks: 131 }),
+    ]);
+    assert_eq!(profiler.folded_stacks(), "0x0001 131\n0x0001;add 180");
+}
+
+#[test]
+fn lines() {
+    let profiler = profile("tests/emulator/sum.asm");
+    let lines = profiler.lines();
+
+    assert_eq!(lines.len(), 12);
+    // Sorted by ticks, then by line.
+    assert!(lines[0].0.ends_with("sum.asm:6"));
+    assert_eq!(lines[0].1, Cost { instructions: 10, ticks: 50 });
+    assert!(lines[11].0.ends_with("sum.asm:10"));
+    assert!(profiler.report().contains("  57.88%           40  add\n"));
+}
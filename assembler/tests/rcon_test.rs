@@ -0,0 +1,72 @@
+use std::{
+    io::ErrorKind,
+    net::{TcpListener, TcpStream},
+    thread,
+};
+
+use lib::{
+    console::{self, Anchor},
+    rcon::{Packet, RconClient, SERVERDATA_AUTH, SERVERDATA_AUTH_RESPONSE, SERVERDATA_EXECCOMMAND, SERVERDATA_RESPONSE_VALUE},
+    rom::RomPlan,
+    PROGRAM_START,
+};
+
+const PASSWORD: &str = "secret";
+
+/// Bytes per response packet of the fake server, small to test responses split into several packets.
+const RESPONSE_PACKET_SIZE: usize = 16;
+
+/// Fake Factorio server which accepts a single client. It answers every command with the
+/// command reversed. The thread returns the commands it received.
+fn start_server() -> (String, thread::JoinHandle<Vec<String>>) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap().to_string();
+    let server = thread::spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+        let mut commands = Vec::new();
+        while let Ok(packet) = Packet::read(&mut stream) {
+            match packet.kind {
+                SERVERDATA_AUTH => {
+                    let id = if packet.body == PASSWORD { packet.id } else { -1 };
+                    reply(&mut stream, packet.id, SERVERDATA_RESPONSE_VALUE, "");
+                    reply(&mut stream, id, SERVERDATA_AUTH_RESPONSE, "");
+                }
+                SERVERDATA_EXECCOMMAND if packet.body.is_empty() => reply(&mut stream, packet.id, SERVERDATA_RESPONSE_VALUE, ""),
+                SERVERDATA_EXECCOMMAND => {
+                    let response = packet.body.chars().rev().collect::<String>();
+                    for chunk in response.as_bytes().chunks(RESPONSE_PACKET_SIZE) {
+                        reply(&mut stream, packet.id, SERVERDATA_RESPONSE_VALUE, std::str::from_utf8(chunk).unwrap());
+                    }
+                    commands.push(packet.body);
+                }
+                kind => panic!("Unexpected packet type {}", kind),
+            }
+        }
+        commands
+    });
+    (address, server)
+}
+
+fn reply(stream: &mut TcpStream, id: i32, kind: i32, body: &str) {
+    Packet { id, kind, body: body.into() }.write(stream).unwrap();
+}
+
+#[test]
+fn execute_collects_split_responses() {
+    let (address, server) = start_server();
+    let loader = console::rom_loader(&RomPlan::new(&[0, 0, 0, 0xEE], PROGRAM_START, 1), &Anchor::Tag("rom".into()));
+    {
+        let mut client = RconClient::connect(&address, PASSWORD).unwrap();
+        assert_eq!(client.execute("/version").unwrap(), "noisrev/");
+        assert_eq!(client.execute(&loader).unwrap(), loader.chars().rev().collect::<String>());
+    }
+    assert_eq!(server.join().unwrap(), vec!["/version".to_string(), loader]);
+}
+
+#[test]
+fn wrong_password_is_rejected() {
+    let (address, server) = start_server();
+    let error = RconClient::connect(&address, "wrong").err().unwrap();
+    assert_eq!(error.kind(), ErrorKind::PermissionDenied);
+    assert!(server.join().unwrap().is_empty());
+}
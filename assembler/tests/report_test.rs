@@ -0,0 +1,47 @@
+mod common;
+
+use lib::{
+    assemble_object,
+    layout::MemoryLayout,
+    link,
+    report::{Block, Report},
+    timing::TimingModel,
+    LinkOptions,
+};
+
+fn create_report(path: &str, layout: Option<&str>, rom_slots: usize) -> Report {
+    let options = LinkOptions {
+        layout: layout.map(|layout| MemoryLayout::read(common::source_path(layout)).unwrap()),
+        ..LinkOptions::default()
+    };
+    let program = link(&[assemble_object(common::source_path(path))], &options);
+    Report::new(&program, &TimingModel::default(), rom_slots)
+}
+
+#[test]
+fn blocks_end_at_jumps_and_their_targets() {
+    let report = create_report("tests/data/misc/output.asm", None, 3);
+    assert_eq!(report.words, 7);
+    assert_eq!(report.rom_combinators, 3);
+    assert_eq!(report.symbols, vec![("0x0001".to_string(), 2), ("loop".to_string(), 5)]);
+    let opcodes = report.opcodes.iter().map(|(opcode, signature, count)| (*opcode, signature.as_str(), *count)).collect::<Vec<_>>();
+    assert_eq!(opcodes, vec![(0x01, "MOV reg, imm", 1), (0x07, "MOV [imm], reg", 1), (0x18, "DEC reg", 1), (0x52, "JNZ label", 1), (0xee, "HALT", 1)]);
+
+    // MOV with an immediate takes an extra word, MOV to memory additionally a memory access.
+    let block = |start, end, instructions, ticks| Block { start, end, instructions, ticks };
+    assert_eq!(report.blocks, vec![block(1, 3, 1, 4), block(3, 7, 3, 12), block(7, 8, 1, 3)]);
+}
+
+#[test]
+fn data_words_are_not_instructions() {
+    // Without ram region, the variables are placed after the code in rom.
+    let report = create_report("tests/sections/variables.asm", None, 1);
+    assert_eq!((report.words, report.data_words, report.rom_combinators), (10, 0, 10));
+    assert_eq!(report.opcodes.iter().map(|(_, _, count)| count).sum::<u32>(), 4);
+    assert_eq!(report.blocks.len(), 1);
+
+    let report = create_report("tests/sections/variables.asm", Some("tests/layout/layout.json"), 1);
+    assert_eq!((report.words, report.data_words), (9, 3));
+    let symbols = report.symbols.iter().map(|(name, words)| (name.as_str(), *words)).collect::<Vec<_>>();
+    assert_eq!(symbols, vec![("0x0001", 9), ("counter", 1), ("table", 2), ("buffer", 0)]);
+}
@@ -0,0 +1,73 @@
+mod common;
+
+use lib::{
+    assemble,
+    blueprint::Blueprint,
+    circuit::Circuit,
+    rom::{self, Location, RomPlan},
+    signal_policy::SignalPolicy,
+    PROGRAM_START,
+};
+
+#[test]
+fn packed_words_are_on_their_signals() {
+    let binary = assemble(common::source_path("tests/data/misc/output.asm"));
+    let words = binary.len() as u32 / 4;
+    for slots in [1, 3, 20] {
+        let rom = RomPlan::new(&binary, PROGRAM_START, slots);
+        assert_eq!(rom.combinator_count(), (words as usize).div_ceil(slots));
+        let selector = rom.selector();
+        assert_eq!((selector.start, selector.slots, selector.rows), (PROGRAM_START, slots, rom.combinator_count()));
+        assert_eq!(selector.signals.len(), slots);
+
+        // Constant combinators are numbered from 1 in the order of the rom.
+        let blueprint = Blueprint::decode(&rom.blueprint().encode()).unwrap();
+        let circuit = Circuit::new(&blueprint).unwrap();
+        for (offset, word) in rom.words().into_iter().enumerate() {
+            let location = rom.locate(PROGRAM_START + offset as u32).unwrap();
+            let output = circuit.output(location.combinator as u32 + 1).unwrap();
+            assert_eq!(output.get(location.signal).copied().unwrap_or(0), word, "slots {}, offset {}", slots, offset);
+        }
+        assert_eq!(rom.locate(PROGRAM_START + words), None);
+        assert_eq!(rom.locate(PROGRAM_START - 1), None);
+
+        assert_eq!(RomPlan::unpack(&blueprint, PROGRAM_START, slots).unwrap(), rom);
+    }
+
+    let rom = RomPlan::new(&binary, PROGRAM_START, 3);
+    assert_eq!(rom.locate(PROGRAM_START + 4), Some(Location { combinator: 1, slot: 1, signal: "signal-6" }));
+    assert_eq!(rom.selector().signals, vec!["signal-5", "signal-6", "signal-7"]);
+    assert_eq!(rom.selector().to_string(), "row = (address - 1) / 3 of 3 rows, slot = (address - 1) % 3 on signal-5, signal-6, signal-7");
+    // A single word per combinator is the memory value, like the console loader writes it.
+    let rom = RomPlan::new(&binary, PROGRAM_START, 1);
+    assert_eq!(rom.locate(PROGRAM_START + 4), Some(Location { combinator: 4, slot: 0, signal: "signal-green" }));
+    assert_eq!(rom.selector().signals, vec!["signal-green"]);
+}
+
+#[test]
+fn unpack_fills_empty_slots_and_rejects_other_signals() {
+    // Zeros in the middle of a combinator leave their slot empty.
+    let binary = [0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0, 2, 0, 0, 0, 0];
+    let rom = RomPlan::new(&binary, PROGRAM_START, 2);
+    let unpacked = RomPlan::unpack(&rom.blueprint(), PROGRAM_START, 2).unwrap();
+    assert_eq!(unpacked.words(), vec![1, 0, 2]);
+
+    assert!(RomPlan::unpack(&rom.blueprint(), PROGRAM_START, 1).is_err());
+    assert!(RomPlan::unpack(&rom.blueprint(), PROGRAM_START, 21).is_err());
+}
+
+#[test]
+fn packed_signals_are_not_used_by_the_cpu() {
+    // Clock and internal signal of MicroArchitecture.md, addresses of the memory banks and the bus signals.
+    let mut cpu_signals = ["signal-C", "signal-pink", "signal-W", "signal-R"].map(String::from).to_vec();
+    for bus in SignalPolicy::default().buses {
+        cpu_signals.extend(bus.signals);
+        cpu_signals.extend(bus.allowed);
+    }
+
+    let signals = RomPlan::new(&[], PROGRAM_START, rom::MAX_SLOTS).selector().signals;
+    assert_eq!(signals.len(), rom::MAX_SLOTS);
+    for signal in signals {
+        assert!(!cpu_signals.iter().any(|cpu_signal| cpu_signal == signal), "{} is used by the cpu", signal);
+    }
+}
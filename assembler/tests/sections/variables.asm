@@ -0,0 +1,19 @@
+; Code and variables are written interleaved,
+; but the linker places them in separate sections.
+.data
+counter:
+    .word 5
+table:
+    .word counter, 0x10
+
+.text
+    MOV A, [counter]
+    MOV B, table
+
+.bss
+buffer:
+    .space 4
+
+.text
+    MOV [buffer], A
+    HALT
@@ -0,0 +1,42 @@
+mod common;
+
+use lib::{assemble_object, layout::MemoryLayout, link, LinkOptions};
+
+fn words(words: &[u32]) -> Vec<u8> {
+    words.iter().copied().flat_map(u32::to_be_bytes).collect()
+}
+
+#[test]
+fn data_after_text() {
+    let object = assemble_object(common::source_path("tests/sections/variables.asm"));
+    let program = link(&[object], &LinkOptions::default());
+
+    assert_eq!(program.binary, words(&[
+        0x0000_0103, 8, // MOV A, [counter]
+        0x0000_0201, 9, // MOV B, table
+        0x0000_0107, 11, // MOV [buffer], A
+        0x0000_00ee, // HALT
+        5, // counter
+        8, 0x10, // table
+    ]));
+    assert!(program.data.is_empty());
+    assert_eq!(program.debug_info.symbol("buffer"), Some(11));
+}
+
+#[test]
+fn data_in_ram() {
+    let layout = MemoryLayout::read(common::source_path("tests/layout/layout.json")).unwrap();
+    let options = LinkOptions { layout: Some(layout), ..LinkOptions::default() };
+    let object = assemble_object(common::source_path("tests/sections/variables.asm"));
+    let program = link(&[object], &options);
+
+    assert_eq!(program.binary, words(&[
+        0x0000_0601, 144, // MOV SP, 144
+        0x0000_0103, 65, // MOV A, [counter]
+        0x0000_0201, 66, // MOV B, table
+        0x0000_0107, 68, // MOV [buffer], A
+        0x0000_00ee, // HALT
+    ]));
+    assert_eq!(program.data_start, 65);
+    assert_eq!(program.data, words(&[5, 65, 0x10]));
+}
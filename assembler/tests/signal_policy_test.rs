@@ -0,0 +1,48 @@
+mod common;
+
+use lib::{
+    blueprint::Blueprint,
+    circuit::{Circuit, WireColor},
+    signal_policy::{SignalPolicy, Violation},
+};
+
+fn violation(bus: &str, color: WireColor, entities: &[u32], tick: Option<u64>) -> Violation {
+    Violation {
+        bus: bus.into(),
+        signal: "signal-pink".into(),
+        color,
+        entities: entities.to_vec(),
+        tick,
+    }
+}
+
+#[test]
+fn internal_signals_on_buses() {
+    let blueprint = Blueprint::read(common::source_path("tests/circuit/signals.txt")).unwrap();
+    let mut circuit = Circuit::new(&blueprint).unwrap();
+    let violations = SignalPolicy::default().check(&mut circuit, 10);
+
+    assert_eq!(
+        violations,
+        [
+            // Constant combinator on the memory wire.
+            violation("memory", WireColor::Red, &[1, 2, 3], Some(0)),
+            // Passed on by the `each` combinator one tick later.
+            violation("memory", WireColor::Green, &[2, 4], Some(1)),
+            // The decider never outputs pink, but it could.
+            violation("registers", WireColor::Red, &[5, 6], None),
+        ]
+    );
+    assert_eq!(violations[2].to_string(), "signal-pink on registers red wire of entities 5, 6 (possible)");
+}
+
+#[test]
+fn allowed_signals() {
+    let blueprint = Blueprint::read(common::source_path("tests/circuit/signals.txt")).unwrap();
+    let mut policy = SignalPolicy::default();
+    for bus in &mut policy.buses {
+        bus.allowed.push("signal-pink".into());
+    }
+
+    assert_eq!(policy.check(&mut Circuit::new(&blueprint).unwrap(), 10), []);
+}
@@ -0,0 +1,35 @@
+mod common;
+
+use lib::{
+    disassembler::Decoder,
+    emulator::Emulator,
+    timing::{seconds, TimingModel},
+};
+
+fn ticks(words: &[u32]) -> u64 {
+    TimingModel::default().ticks(&Decoder::new().decode(words).unwrap())
+}
+
+#[test]
+fn default_model() {
+    assert_eq!(ticks(&[0x0002_0102]), 3); // MOV A, B
+    assert_eq!(ticks(&[0x0000_0101, 42]), 4); // MOV A, 42
+    assert_eq!(ticks(&[0x0000_0103, 42]), 6); // MOV A, [42]
+    assert_eq!(ticks(&[0x0000_0005, 42, 7]), 7); // MOV [42], 7
+    assert_eq!(ticks(&[0x0000_0070]), 5); // CALL
+}
+
+#[test]
+fn configured_model() {
+    let program = common::link_source("tests/emulator/sum.asm");
+    let mut emulator = Emulator::with_program(&program);
+    emulator.set_timing(TimingModel::read(common::source_path("tests/timing/model.json")).unwrap());
+
+    let mut call_ticks = 0;
+    emulator.run_with(1000, |_, step| if step.instruction.opcode == 0x70 { call_ticks += step.ticks }).unwrap();
+
+    // 3 MOV with 1 tick for opcode 0x01, 10 loop iterations, MOV [0x80], A and HALT.
+    assert_eq!(emulator.ticks, 3 + 10 * (6 + 3 + 3 + 2 + 3 + 2 + 2) + 3 + 2);
+    assert_eq!(call_ticks, 10 * 6);
+    assert_eq!(seconds(emulator.ticks, 2.0), 218.0 / 120.0);
+}
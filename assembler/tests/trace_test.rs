@@ -0,0 +1,50 @@
+mod common;
+
+use lib::{
+    emulator::Emulator,
+    trace::{diff, Divergence, Tracer},
+};
+
+fn trace(path: &str) -> Vec<String> {
+    let program = common::link_source(path);
+    let mut emulator = Emulator::with_program(&program);
+    let mut tracer = Tracer::new(Vec::new(), &emulator);
+    assert_eq!(emulator.run_with(1000, |emulator, step| tracer.record(emulator, step).unwrap()), Ok(true));
+    String::from_utf8(tracer.into_inner()).unwrap().lines().map(String::from).collect()
+}
+
+#[test]
+fn record_trace() {
+    let trace = trace("tests/emulator/sum.asm");
+
+    assert_eq!(trace.len(), 75);
+    assert_eq!(trace[..8], [
+        "0001: MOV SP, 256 ; SP=256",
+        "0003: MOV A, 0",
+        "0005: MOV B, 10 ; B=10",
+        "0007: CALL +6 ; SP=255 [0x0100]=7",
+        "000d: PUSH B ; SP=254 [0x00ff]=10",
+        "000e: POP C ; C=10 SP=255",
+        "000f: ADD A, C ; A=10",
+        "0010: RET ; SP=256",
+    ]);
+    assert_eq!(trace[8], "0008: DEC B ; B=9");
+    assert_eq!(trace[74], "000c: HALT");
+}
+
+#[test]
+fn trace_divergence() {
+    let left = trace("tests/data/jump/55_jle_label.asm");
+    let right = trace("tests/data/jump/56_jgt_label.asm");
+
+    assert_eq!(diff(&left, &left), None);
+    assert_eq!(diff(&left, &right), Some(Divergence {
+        line: 2,
+        common: Some("0001: CMP A, 0 ; Z=1".into()),
+        left: Some("0003: JLE +3".into()),
+        right: Some("0003: JGT +3".into()),
+    }));
+
+    // One trace ends early.
+    assert_eq!(diff(&left[..3], &left).map(|divergence| (divergence.line, divergence.left)), Some((4, None)));
+}